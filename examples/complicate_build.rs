@@ -1,5 +1,6 @@
 #![allow(clippy::disallowed_names)]
 mod scope {
+    use typed_builder::state::{Optional, Set};
     use typed_builder::TypedBuilder;
 
     #[derive(Debug, PartialEq, TypedBuilder)]
@@ -20,13 +21,10 @@ mod scope {
 
     // Customize build method to add complicated logic.
     //
-    // The signature might be frightening at first glance,
-    // but we don't need to infer this whole ourselves.
-    //
-    // We can use `cargo expand` to show code expanded by `TypedBuilder`,
-    // copy the generated `__build` method, and modify the content of the build method.
-    #[allow(non_camel_case_types)]
-    impl<__z: typed_builder::Optional<i32>, __y: typed_builder::Optional<Option<i32>>> FooBuilder<((i32,), __y, __z)> {
+    // The last generic parameter of the builder is a tuple with the state of each field. The
+    // mandatory `x` must be `Set`, and the fields with defaults can be in any state that resolves
+    // to a value - which is what `Optional` is implemented for.
+    impl<Y: Optional<Option<i32>>, Z: Optional<i32>> FooBuilder<(Set<i32>, Y, Z)> {
         pub fn build(self) -> Bar {
             let foo = self.__build();
             Bar {
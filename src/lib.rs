@@ -1,7 +1,5 @@
 #![no_std]
 
-use core::ops::FnOnce;
-
 /// `TypedBuilder` is not a real type - deriving it will generate a `::builder()` method on your
 /// struct that will return a compile-time checked builder. Set the fields using setters with the
 /// same name as the struct's fields and call `.build()` when you are done to create your object.
@@ -52,17 +50,100 @@ use core::ops::FnOnce;
 /// // Foo::builder().x(1).y(2).y(3);
 /// ```
 ///
+/// Tuple structs are supported as well. Their setters are named after the positions of the fields
+/// (`_0`, `_1`, ...), unless a name is given with `#[builder(setter(name = ...))]`:
+///
+/// ```
+/// use typed_builder::TypedBuilder;
+///
+/// #[derive(PartialEq, TypedBuilder)]
+/// struct Point(#[builder(setter(name = x))] i32, #[builder(default)] i32);
+///
+/// assert!(Point::builder().x(1).build() == Point(1, 0));
+/// assert!(Point::builder()._1(2).x(1).build() == Point(1, 2));
+/// ```
+///
+/// Deriving `TypedBuilder` on an enum generates a separate builder for each variant that has
+/// fields. The builder of a variant is created with a method named after the variant (e.g.
+/// `circle_builder()` for `Circle`), and its `build()` method returns the enum:
+///
+/// ```
+/// use typed_builder::TypedBuilder;
+///
+/// #[derive(PartialEq, TypedBuilder)]
+/// enum Shape {
+///     Circle { radius: f64 },
+///     Rect {
+///         width: f64,
+///         #[builder(default = width)]
+///         height: f64,
+///     },
+/// }
+///
+/// assert!(Shape::circle_builder().radius(1.0).build() == Shape::Circle { radius: 1.0 });
+/// assert!(Shape::rect_builder().width(2.0).build() == Shape::Rect { width: 2.0, height: 2.0 });
+/// ```
+///
+/// With `#[builder(into_builder)]`, a struct can also be turned back into a builder, to build a copy
+/// of it with some of the fields changed. `into_builder()` consumes the struct and `to_builder()`
+/// clones it, and both return a builder on which all the fields are set, and whose setters replace
+/// the values of the fields instead of failing because they are already set. Fields with
+/// `setter(skip)` are given their default again, and mutators that require fields are only
+/// available after these fields are set with their setters:
+///
+/// ```
+/// use typed_builder::TypedBuilder;
+///
+/// #[derive(Clone, PartialEq, TypedBuilder)]
+/// #[builder(into_builder)]
+/// struct Foo {
+///     x: i32,
+///     #[builder(default = 20)]
+///     y: i32,
+/// }
+///
+/// let foo = Foo::builder().x(1).y(2).build();
+/// assert!(foo.to_builder().y(3).build() == Foo { x: 1, y: 3 });
+/// assert!(foo.into_builder().x(4).build() == Foo { x: 4, y: 2 });
+/// ```
+///
+/// Fields that are set can also be moved back out of the builder, which leaves them unset so that
+/// they can be set again or left to their default. For each field `x`, `take_x()` returns the value
+/// together with the builder, and `unset_x()` drops the value. Both have the visibility of the
+/// field, so that fields that are not visible can't be read from the builder:
+///
+/// ```
+/// use typed_builder::TypedBuilder;
+///
+/// #[derive(PartialEq, TypedBuilder)]
+/// struct Foo {
+///     x: i32,
+///     #[builder(default = 20)]
+///     y: i32,
+/// }
+///
+/// let (x, builder) = Foo::builder().x(1).y(2).take_x();
+/// assert!(builder.x(x + 1).unset_y().build() == Foo { x: 2, y: 20 });
+/// ```
+///
 /// # Customization with attributes
 ///
 /// In addition to putting `#[derive(TypedBuilder)]` on a type, you can specify a `#[builder(...)]`
 /// attribute on the type, and on any fields in it.
 ///
+/// On enums, the `#[builder(...)]` attribute can also be put on **variants**. It accepts the same
+/// values as the attribute on the type, and is applied on top of it for the builder of that variant.
+/// Since each variant has its own builder, the names of the builder type and the builder method can
+/// only be customized on the variants.
+///
 /// On the **type**, the following values are permitted:
 ///
 /// - `doc`: enable documentation of the builder type. By default, the builder type is given
 ///   `#[doc(hidden)]`, so that the `builder()` method will show `FooBuilder` as its return type,
 ///   but it won't be a link. If you turn this on, the builder type and its `build` method will get
-///   sane defaults. The field methods on the builder will be undocumented by default.
+///   sane defaults. The field methods on the builder will be undocumented by default. The module
+///   with the traits for the states of the builder's fields - see [`state`] - is documented as
+///   well.
 ///
 /// - `crate_module_path`: This is only needed when `typed_builder` is reexported from another
 ///   crate - which usually happens when another macro uses it. In that case, it is the
@@ -84,12 +165,112 @@ use core::ops::FnOnce;
 ///     `build()` method of the builder type. Setting this implies `doc`.
 ///
 ///
+/// - The `builder_method(...)` subsection also has:
+///   - `args(...)`: fields that the builder method takes as arguments, in the given order, and
+///     that are set in the builder it creates. The arguments are those of the fields' setters, so
+///     `into`, `strip_option` and `transform` apply to them - but the fields can't be `nested`,
+///     `each` or `strip_bool`. The values of the fields can be used by the `init` expressions of
+///     the fields that are set `via_mutators`. Since the trait can't pass the arguments, the struct
+///     doesn't implement [`HasBuilder`] - and can't be flattened. The runtime builder method takes
+///     the same arguments, and the runtime builder doesn't implement `Default`.
+///
+///   ```
+///   use typed_builder::TypedBuilder;
+///
+///   #[derive(Debug, PartialEq, TypedBuilder)]
+///   #[builder(builder_method(args(id, name)))]
+///   struct User {
+///       id: u32,
+///       #[builder(setter(into))]
+///       name: String,
+///       #[builder(default)]
+///       admin: bool,
+///   }
+///
+///   assert_eq!(
+///       User::builder(1, "root").admin(true).build(),
+///       User { id: 1, name: "root".to_owned(), admin: true },
+///   );
+///   ```
+///
+/// - The `builder_type(...)` subsection also has:
+///   - `stable`: generate aliases for the states of the builder that downstream code is most
+///     likely to name, which don't change when fields with defaults are added to the struct.
+///     `FooBuilder<foo_builder::Empty>` is `FooBuilder` as it is created, and
+///     `FooBuilder<foo_builder::Complete>` is `FooBuilder` with all the required fields set and
+///     the fields with defaults unset. The aliases are declared in the builder module, and take
+///     the generic parameters of the struct. The states in between can be named from them with the
+///     traits of the fields - e.g. `<foo_builder::Complete as foo_builder::YState>::WithValue<i32>`
+///     once `y` is set. Cannot be combined with `flatten`.
+///   - `module = ...`: the name of the module with the traits for the states of the fields of
+///     the builder - see [`state`]. Defaults to the name of the builder in snake case
+///     (`foo_builder` for `FooBuilder`), and can be changed when that name is already taken.
+///
+///   ```
+///   mod api {
+///       use typed_builder::TypedBuilder;
+///
+///       #[derive(TypedBuilder)]
+///       #[builder(builder_type(stable))]
+///       pub struct Foo {
+///           x: i32,
+///           #[builder(default)]
+///           y: i32,
+///       }
+///
+///       // Adding another field with a default to `Foo` won't change these signatures.
+///       pub fn new_foo(x: i32) -> FooBuilder<foo_builder::Complete> {
+///           Foo::builder().x(x)
+///       }
+///
+///       pub fn new_foo_with_y(
+///           x: i32,
+///           y: i32,
+///       ) -> FooBuilder<<foo_builder::Complete as foo_builder::YState>::WithValue<i32>> {
+///           new_foo(x).y(y)
+///       }
+///   }
+///
+///   let foo = api::new_foo(1).y(2).build();
+///   let foo = api::new_foo_with_y(1, 2).build();
+///   ```
+///
 /// - The `build_method(...)` subsection also has:
 ///   - `into` or `into = ...`: change the output type of the builder. When a specific value/type
 ///     is set via the assignment, this will be the output type of the builder. If no specific
 ///     type is set, but `into` is specified, the return type will be generic and the user can
 ///     decide which type shall be constructed. In both cases an [`Into`] conversion is required to
 ///     be defined from the original type to the target type.
+///   - `error = ...`: make the build method fallible, returning `Result<..., ...>` with the given
+///     error type. The default name of a fallible build method is `try_build` instead of `build`.
+///     Cannot be combined with a generic `into` - use `into = ...` to pick the target type.
+///   - `validate = ...`: a function or closure that takes a reference to the built instance
+///     (before any `into` conversion) and returns `Result<(), E>` where the `error` type
+///     implements `From<E>`. Requires `error` to be set.
+///
+///   ```
+///   use typed_builder::TypedBuilder;
+///
+///   #[derive(Debug, PartialEq)]
+///   struct RangeError;
+///
+///   #[derive(Debug, PartialEq, TypedBuilder)]
+///   #[builder(build_method(validate = Range::check, error = RangeError))]
+///   struct Range {
+///       start: u32,
+///       #[builder(default = start + 10)]
+///       end: u32,
+///   }
+///
+///   impl Range {
+///       fn check(&self) -> Result<(), RangeError> {
+///           if self.start <= self.end { Ok(()) } else { Err(RangeError) }
+///       }
+///   }
+///
+///   assert_eq!(Range::builder().start(1).try_build(), Ok(Range { start: 1, end: 11 }));
+///   assert_eq!(Range::builder().start(2).end(1).try_build(), Err(RangeError));
+///   ```
 ///
 /// - `field_defaults(...)` is structured like the `#[builder(...)]` attribute you can put on the
 ///   fields and sets default options for fields of the type. If specific field need to revert some
@@ -125,18 +306,125 @@ use core::ops::FnOnce;
 /// - `mutators(...)` takes functions, that can mutate fields inside of the builder.
 ///   See [mutators](#mutators) for details.
 ///
+/// - `runtime`: also generate a builder that checks its required fields when it is built rather
+///   than at compile time, for builders that are filled in by loops and conditionals. It is
+///   created with `runtime_builder()` (`<variant_name>_runtime_builder()` for enum variants) and
+///   named like the builder with `Runtime` added - e.g. `FooRuntimeBuilder`. Its setters take
+///   `&mut self` and can be called repeatedly, with the last value winning, and they honour the
+///   same setter settings as the setters of the regular builder. Its build method also takes
+///   `&mut self`, moving the values out of the builder, and returns a `Result` whose error is a
+///   [`MissingFields`] naming the required fields that were not set - in which case the builder is
+///   left untouched. With `build_method(error = ...)` - which `validate` requires - its build method
+///   returns that error type instead, so the error type must also implement `From<MissingFields>`.
+///   The runtime builder tracks which required fields are set in a `u128`, so it supports up to
+///   128 required fields - more are a compile error. Mutators and flattened fields are not
+///   supported by the runtime builder - fields set `via_mutators` get their initial value.
+///
+///   ```
+///   use typed_builder::TypedBuilder;
+///
+///   #[derive(Debug, PartialEq, TypedBuilder)]
+///   #[builder(runtime)]
+///   struct Foo {
+///       x: i32,
+///       #[builder(default, setter(strip_option))]
+///       y: Option<i32>,
+///       z: i32,
+///   }
+///
+///   let mut builder = Foo::runtime_builder();
+///   for i in 0..3 {
+///       builder.x(i);
+///   }
+///   let error = builder.build().unwrap_err();
+///   assert_eq!(error.to_string(), "missing required fields on `FooRuntimeBuilder`: `z`");
+///   assert_eq!(builder.z(1).build(), Ok(Foo { x: 2, y: None, z: 1 }));
+///   ```
+///
+/// - `ordered`: make the builder a step builder. The required fields must be set in the order
+///   they are declared in, and the setters of the fields with defaults are only available once all
+///   the required fields are set - so in each state, the only other setter is that of the next
+///   required field, and the setters of the fields that come later don't exist yet. A required
+///   field can only be unset (with `take_<field>()` or `unset_<field>()`) while the required
+///   fields after it are unset. Fields that the builder method or a preset sets are skipped, but
+///   the fields before them must still be set first. The setters of a builder created with
+///   `into_builder()` can be called in any order. Flattened fields are not supported.
+///
+///   ```
+///   use typed_builder::TypedBuilder;
+///
+///   #[derive(Debug, PartialEq, TypedBuilder)]
+///   #[builder(ordered)]
+///   struct Foo {
+///       x: i32,
+///       #[builder(default)]
+///       y: i32,
+///       z: i32,
+///   }
+///
+///   assert_eq!(Foo::builder().x(1).z(2).y(3).build(), Foo { x: 1, y: 3, z: 2 });
+///
+///   // This will not compile - because `z` is set before `x`:
+///   // Foo::builder().z(2).x(1);
+///   ```
+///
+/// - `flattenable`: allow other structs to flatten this one - see `flatten` below. This generates
+///   a hidden macro named after the struct, and an implementation of a hidden trait for each of
+///   the setters. Requires the builder method to take no arguments, and the builder method, the
+///   builder type and the build method to keep their default visibility.
+///
+/// - `into_builder`: generate the `into_builder()` and `to_builder()` methods, which turn the struct
+///   back into a builder - see above. They have the visibility of the builder method, so the
+///   fields can be read back from the builder wherever it can be created. Only supported for
+///   structs, and a flattened field must be of a type that has them as well.
+///
+/// - `presets(name(field = ..., ...), ...)`: generate another builder method for each preset,
+///   named after it (e.g. `dev_builder()` for `dev`), which creates the builder with the fields of
+///   the preset prefilled. Like with `into_builder()`, prefilled fields count as set, and their
+///   setters replace the values of the preset. The builder methods of the presets take the same
+///   arguments as the regular builder method. Only supported for structs.
+///
+///   ```
+///   use typed_builder::TypedBuilder;
+///
+///   #[derive(Debug, PartialEq, TypedBuilder)]
+///   #[builder(presets(dev(host = "localhost", debug = true), prod(host = "example.com")))]
+///   struct Config {
+///       host: &'static str,
+///       #[builder(default = 80)]
+///       port: u16,
+///       #[builder(default)]
+///       debug: bool,
+///   }
+///
+///   assert_eq!(Config::dev_builder().build(), Config { host: "localhost", port: 80, debug: true });
+///   assert_eq!(
+///       Config::prod_builder().port(443).build(),
+///       Config { host: "example.com", port: 443, debug: false },
+///   );
+///   ```
+///
 /// On each **field**, the following values are permitted:
 ///
 /// - `default`: make the field optional, defaulting to `Default::default()`. This requires that
 ///   the field type implement `Default`. Mutually exclusive with any other form of default.
 ///
-/// - `default = ...`: make the field optional, defaulting to the expression `...`.
+/// - `default = ...`: make the field optional, defaulting to the expression `...`. The expression
+///   can refer to the other fields by name, regardless of the order they are declared in - the
+///   defaults are resolved after the fields they refer to, and defaults that refer to each other in
+///   a cycle are an error. Names that are called as functions, or bound inside the expression,
+///   don't refer to fields.
 ///
 /// - `default_code = "..."`: make the field optional, defaulting to the expression `...`. Note that
-///    you need to enclose it in quotes, which allows you to use it together with other custom
-///    derive proc-macro crates that complain about "expected literal".
-///    Note that if `...` contains a string, you can use raw string literals to avoid escaping the
-///    double quotes - e.g. `#[builder(default_code = r#""default text".to_owned()"#)]`.
+///   you need to enclose it in quotes, which allows you to use it together with other custom
+///   derive proc-macro crates that complain about "expected literal".
+///   Note that if `...` contains a string, you can use raw string literals to avoid escaping the
+///   double quotes - e.g. `#[builder(default_code = r#""default text".to_owned()"#)]`.
+///
+/// - `validate = ...`: a function or closure that takes a reference to the field's value and returns
+///   `Result<(), E>`. It is called with the resolved value - including values that came from the
+///   `default` - when the builder is finalised, and its error is converted into the type set by
+///   `build_method(error = ...)`, which is required when using this setting.
 ///
 /// - `via_mutators`: initialize the field when constructing the builder, useful in combination
 ///   with [mutators](#mutators).
@@ -147,6 +435,37 @@ use core::ops::FnOnce;
 /// - `mutators(...)` takes functions, that can mutate fields inside of the builder.
 ///   Mutators specified on a field, mark this field as required, see [mutators](#mutators) for details.
 ///
+/// - `flatten`: for fields whose type is a struct that also derives `TypedBuilder`, this puts the
+///   setters of that struct's builder on this builder, instead of a setter for the field. They keep
+///   the names, settings and defaults they have on that struct's builder - including its
+///   `field_defaults`, prefixes and suffixes - and the build method requires the fields that are
+///   required there. Setters with the same name as other setters of the builder are an error.
+///   The flattened struct must be defined in the same crate and marked `#[builder(flattenable)]`,
+///   and its builder must not use `setter(nested)`.
+///
+///   ```
+///   use typed_builder::TypedBuilder;
+///
+///   #[derive(TypedBuilder)]
+///   #[builder(flattenable, field_defaults(setter(prefix = "net_")))]
+///   struct NetConfig {
+///       #[builder(default = 30)]
+///       timeout: u32,
+///       host: &'static str,
+///   }
+///
+///   #[derive(TypedBuilder)]
+///   struct Config {
+///       name: &'static str,
+///       #[builder(flatten)]
+///       net: NetConfig,
+///   }
+///
+///   let config = Config::builder().name("app").net_host("localhost").build();
+///   assert_eq!(config.net.host, "localhost");
+///   assert_eq!(config.net.timeout, 30);
+///   ```
+///
 /// - `setter(...)`: settings for the field setters. The following values are permitted inside:
 ///
 ///   - `doc = "..."`: sets the documentation for the field's setter on the builder type. This will be
@@ -173,6 +492,105 @@ use core::ops::FnOnce;
 ///     transformed into the field type using the expression `expr`. The transformation is performed
 ///     when the setter is called.
 ///
+///   - `name = ...`: use the given name for the setter instead of the field's name. For fields of
+///     tuple structs, this also becomes the name by which `default` expressions and mutators can
+///     refer to the field - instead of `_0`, `_1`, ... Not allowed in `field_defaults`.
+///
+///   - `each = "..."`: for collection fields (`Vec`, `HashSet`, `BTreeMap` and any other type that
+///     implements `Default`, `IntoIterator` and `Extend` of its own items), this adds a setter with
+///     the given name that adds a single item to the collection, and turns the field's own setter
+///     into one that adds all the items of an iterator. Both can be called any number of times.
+///     The first item is added to an empty collection - the `default` of the field, if one is set,
+///     is only used when no items are added, since it may refer to fields that are not set yet.
+///     With `into`, the setters convert each item into the item type of the collection.
+///
+///     ```
+///     use std::collections::BTreeMap;
+///     use typed_builder::TypedBuilder;
+///
+///     #[derive(TypedBuilder)]
+///     struct Command {
+///         #[builder(setter(each = "arg", into))]
+///         args: Vec<String>,
+///         #[builder(setter(each = "env"))]
+///         envs: BTreeMap<&'static str, &'static str>,
+///     }
+///
+///     let command = Command::builder().arg("-v").args(["-o", "out"]).env(("LANG", "C")).build();
+///     assert_eq!(command.args, ["-v", "-o", "out"]);
+///     assert_eq!(command.envs["LANG"], "C");
+///     assert!(Command::builder().build().args.is_empty());
+///     ```
+///
+///   - `nested`: for fields whose type also derives `TypedBuilder`, this makes the setter take a
+///     closure that receives a fresh builder of the field's type and returns it after calling its
+///     setters. The setter then builds the field from it, so the closure must set all of the
+///     required fields of the inner builder. Combinable with `strip_option`, in which case the
+///     builder is of the type inside the `Option`.
+///
+///     ```
+///     use typed_builder::TypedBuilder;
+///
+///     #[derive(Debug, PartialEq, TypedBuilder)]
+///     struct Point {
+///         x: i32,
+///         #[builder(default)]
+///         y: i32,
+///     }
+///
+///     #[derive(TypedBuilder)]
+///     struct Line {
+///         #[builder(setter(nested))]
+///         start: Point,
+///         #[builder(setter(nested))]
+///         end: Point,
+///     }
+///
+///     let line = Line::builder().start(|p| p.x(1)).end(|p| p.x(2).y(3)).build();
+///     assert_eq!(line.start, Point { x: 1, y: 0 });
+///     assert_eq!(line.end, Point { x: 2, y: 3 });
+///     ```
+///
+///   - `overridable`: allow calling the setter again after the field is set, replacing the value
+///     that was set before. Without it, setting a field twice is a compile error. This has no
+///     effect on `each` setters, which can always be called repeatedly.
+///
+///     ```
+///     use typed_builder::TypedBuilder;
+///
+///     #[derive(TypedBuilder)]
+///     struct Request {
+///         url: &'static str,
+///         #[builder(default = 3, setter(overridable))]
+///         retries: u32,
+///     }
+///
+///     let base = Request::builder().retries(5);
+///     assert_eq!(base.retries(1).url("/").build().retries, 1);
+///     ```
+///
+///   - `getter` or `getter(name = ..., doc = "...")`: once the field is set, the builder gets a
+///     method that returns a reference to its value - named `get_<field>` unless a `name` is
+///     given. Fields set `via_mutators` always have a getter, since they are always set. The
+///     getter has the visibility of the field.
+///
+///     ```
+///     use typed_builder::TypedBuilder;
+///
+///     #[derive(TypedBuilder)]
+///     struct Rect {
+///         #[builder(setter(getter))]
+///         width: u32,
+///         #[builder(setter(getter(name = height_so_far)))]
+///         height: u32,
+///     }
+///
+///     let builder = Rect::builder().width(3);
+///     let height = builder.get_width() * 2;
+///     let builder = builder.height(height);
+///     assert_eq!(*builder.height_so_far(), 6);
+///     ```
+///
 ///   - `prefix = "..."` prepends the setter method with the specified prefix. For example, setting
 ///     `prefix = "with_"` results in setters like `with_x` or `with_y`. This option is combinable
 ///     with `suffix = "..."`.
@@ -182,8 +600,8 @@ use core::ops::FnOnce;
 ///     with `prefix = "..."`.
 ///
 ///   - `mutable_during_default_resolution`: when expressions in `default = ...` field attributes
-///     are evaluated, this field will be mutable, allowing earlier-defined fields to be mutated by
-///     later-defined fields.
+///     are evaluated, this field will be mutable, allowing it to be mutated by the
+///     defaults that refer to it.
 ///     **Warning** - Use this feature with care! If the field that mutates the previous field in
 ///     its `default` expression is set via a setter, that mutation will not happen.
 ///
@@ -197,6 +615,23 @@ use core::ops::FnOnce;
 /// Mutators on a field, result in them automatically making the field required, i.e., it needs to be
 /// marked as `via_mutators`, or its setter be called. Appart from that, they behave identically.
 ///
+/// A mutator can also set fields, declared using `#[mutator(sets = [field1, field2, ...])]`. It is
+/// only available when their setters could be called, and afterwards they are set, just as if their
+/// setters had been called. Inside the mutator they start out with their previous value - when the
+/// builder was prefilled, or the field is overridable and already set - and with
+/// `Default::default()` otherwise, so their types must implement `Default`. The mutator is expected
+/// to assign them. Fields that are set item by item with `setter(each = "...")` can only be set by a
+/// mutator while they are unset.
+///
+/// A mutator can be fallible by returning `Result<(), E>`. The method of the builder then returns
+/// `Result<FooBuilder<...>, E>`, so that the error can be propagated with `?` mid-chain. Aliases of
+/// `Result` work too, as long as they are named `Result` and `()` is their first generic argument -
+/// a mutator that returns `io::Result<()>` gives `io::Result<FooBuilder<...>>`. Mutators cannot
+/// return anything else.
+///
+/// Mutators can use the generic parameters of the struct - including lifetimes and const
+/// generics - in their signatures and bodies.
+///
 /// ```
 /// use typed_builder::TypedBuilder;
 ///
@@ -232,25 +667,463 @@ use core::ops::FnOnce;
 ///     Struct::builder().x(2).x_into_b().x_into_b().x_into_b_field().inc_a(2).build(),
 ///     Struct {x: 2, a: 3, b: vec![2, 2, 2]});
 /// ```
+///
+/// ```
+/// use typed_builder::TypedBuilder;
+///
+/// #[derive(PartialEq, Debug, TypedBuilder)]
+/// #[builder(mutators(
+///     // Sets both `host` and `port` - their setters are unavailable afterwards.
+///     #[mutator(sets = [host, port])]
+///     fn from_url(&mut self, url: &str) {
+///         let (host, port) = url.split_once(':').unwrap();
+///         self.host = host.to_owned();
+///         self.port = port.parse().unwrap();
+///     }
+/// ))]
+/// struct Server {
+///     host: String,
+///     port: u16,
+/// }
+///
+/// assert_eq!(
+///     Server::builder().from_url("localhost:8080").build(),
+///     Server { host: "localhost".to_owned(), port: 8080 });
+/// ```
+///
+/// ```
+/// use typed_builder::TypedBuilder;
+///
+/// #[derive(PartialEq, Debug, TypedBuilder)]
+/// #[builder(mutators(
+///     #[mutator(sets = [port])]
+///     fn parse_port(&mut self, port: &str) -> Result<(), std::num::ParseIntError> {
+///         self.port = port.parse()?;
+///         Ok(())
+///     }
+/// ))]
+/// struct Server {
+///     port: u16,
+/// }
+///
+/// fn server(port: &str) -> Result<Server, std::num::ParseIntError> {
+///     Ok(Server::builder().parse_port(port)?.build())
+/// }
+///
+/// assert_eq!(server("8080"), Ok(Server { port: 8080 }));
+/// assert!(server("http").is_err());
+/// ```
 pub use typed_builder_macro::TypedBuilder;
 
+/// Generate a builder for calling a function.
+///
+/// The function is replaced by a function with the same name and visibility that creates the
+/// builder. The builder has a setter for each of the function's arguments, and its `call()` method
+/// calls the original function with them:
+///
+/// ```
+/// #[typed_builder::builder_fn]
+/// fn connect(
+///     #[builder(setter(into))] host: String,
+///     #[builder(default = 80)] port: u16,
+///     #[builder(default, setter(strip_option))] timeout: Option<u32>,
+/// ) -> String {
+///     format!("{host}:{port} {timeout:?}")
+/// }
+///
+/// assert_eq!(connect().host("localhost").call(), "localhost:80 None");
+/// assert_eq!(connect().port(8080).timeout(5).host("localhost").call(), "localhost:8080 Some(5)");
+/// ```
+///
+/// The arguments accept the same `#[builder(...)]` attributes as the fields of a struct deriving
+/// [`TypedBuilder`], and the arguments of `builder_fn` itself accept the same values as the
+/// attribute on the type (e.g. `#[builder_fn(builder_type(name = ConnectionBuilder))]`). The
+/// differences from [`TypedBuilder`] are:
+///
+/// - The builder type is named after the function - `connect` gets a `ConnectBuilder`.
+/// - The build method is named `call` by default (or `try_call` when `build_method(error = ...)`
+///   is set). For an `async fn`, it is also `async`.
+/// - The documentation of the function is moved to the function that creates the builder.
+/// - Arguments must be bound to plain identifiers, and cannot use `impl Trait` types.
+/// - Lifetimes elided in the types of the arguments become lifetime parameters of the builder.
+///   This does not include lifetime parameters hidden in paths - write `Cow<'_, str>` instead of
+///   `Cow<str>`.
+/// - `build_method(validate = ...)` is not supported - validate the arguments instead.
+///
+/// ```
+/// #[typed_builder::builder_fn(builder_type(name = GreetingBuilder))]
+/// async fn greet<'a>(name: &'a str, #[builder(default = "Hello")] greeting: &str) -> String {
+///     format!("{greeting}, {name}!")
+/// }
+///
+/// # util::block_on(async {
+/// let builder: GreetingBuilder<_> = greet().name("World");
+/// assert_eq!(builder.call().await, "Hello, World!");
+/// # });
+/// # mod util {
+/// #     include!("../tests/util/mod.rs");
+/// # }
+/// ```
+pub use typed_builder_macro::builder_fn;
+
+/// Generate builders for the methods of an `impl` block that are marked with `#[builder_fn]`.
+///
+/// Each marked method is replaced by a method that creates a builder, whose build method calls
+/// the original method. This allows keeping the logic of a constructor in the constructor, while
+/// its callers get named arguments that are checked at compile time:
+///
+/// ```
+/// #[derive(Debug, PartialEq)]
+/// struct Rectangle {
+///     width: u32,
+///     height: u32,
+/// }
+///
+/// #[typed_builder::builder_impl]
+/// impl Rectangle {
+///     #[builder_fn]
+///     fn new(width: u32, #[builder(default = width)] height: u32) -> Self {
+///         assert!(width > 0 && height > 0);
+///         Self { width, height }
+///     }
+///
+///     #[builder_fn]
+///     fn scaled(&self, #[builder(default = 1)] horizontal: u32, #[builder(default = 1)] vertical: u32) -> Self {
+///         Self::builder()
+///             .width(self.width * horizontal)
+///             .height(self.height * vertical)
+///             .build()
+///     }
+/// }
+///
+/// let square = Rectangle::builder().width(2).build();
+/// assert_eq!(square, Rectangle { width: 2, height: 2 });
+/// assert_eq!(square.scaled().vertical(3).call(), Rectangle { width: 2, height: 6 });
+/// ```
+///
+/// `#[builder_fn(...)]` accepts the same settings as [`builder_fn`], and the arguments of the
+/// method accept the same `#[builder(...)]` attributes. The differences are:
+///
+/// - The builder of `new` is created with `builder()` and named after the type - `FooBuilder`.
+///   Other methods keep their name, and their builder is named after both - `Foo::bar` gets a
+///   `FooBarBuilder`.
+/// - Methods without a receiver that return `Self` are considered constructors, and their build
+///   method is named `build` (or `try_build`) like the build method of a derived builder. The
+///   build method of other methods is `call` (or `try_call`).
+/// - Methods with a receiver (`self`, `&self`, `&mut self`, etc.) get a builder method with the
+///   same receiver, and the builder holds on to it until it calls the method.
+pub use typed_builder_macro::builder_impl;
+
+/// The states of the fields of a builder.
+///
+/// The last generic parameter of a builder is a tuple with the state of each of its fields, which
+/// is [`Unset`](state::Unset) until the field is set and [`Set<T>`](state::Set) after it is. A
+/// builder created with `into_builder()` or `to_builder()` starts with its fields
+/// [`Prefilled<T>`](state::Prefilled), which allows setting them again.
+///
+/// Methods that work in any state of some of the fields can be added to a builder by making it
+/// generic over the states of these fields. For each field `x`, the module of the builder (named
+/// after it, e.g. `foo_builder` for `FooBuilder`, unless named with `builder_type(module = ...)`)
+/// has an `XCanSet` trait, which is implemented for the states of the builder in which `x` can be
+/// set - including after `into_builder()`, and after `x` is set when its setter is `overridable` -
+/// and the `XIsUnset` and `XIsSet` traits, which are implemented for the states of the builder in
+/// which `x` is not set and is set. All of them extend `XState`, whose `WithValue<T>` and
+/// `WithoutValue` are the states of the builder after `x` is set or unset:
+///
+/// ```
+/// use typed_builder::state::{Set, Unset};
+/// use typed_builder::TypedBuilder;
+///
+/// #[derive(Debug, PartialEq, TypedBuilder)]
+/// struct Foo {
+///     #[builder(setter(getter))]
+///     x: i32,
+///     #[builder(default)]
+///     y: i32,
+/// }
+///
+/// impl<S: foo_builder::XCanSet> FooBuilder<S> {
+///     fn x_from_str(self, x: &str) -> FooBuilder<S::WithValue<i32>> {
+///         self.x(x.parse().unwrap())
+///     }
+/// }
+///
+/// impl<S: foo_builder::XIsSet<Value = i32>> FooBuilder<S> {
+///     fn x_is_positive(&self) -> bool {
+///         *self.get_x() > 0
+///     }
+/// }
+///
+/// let builder: FooBuilder<(Set<i32>, Unset)> = Foo::builder().x_from_str("1");
+/// assert!(builder.x_is_positive());
+/// assert_eq!(builder.y(2).build(), Foo { x: 1, y: 2 });
+/// ```
+pub mod state {
+    /// The state of a field that is not set.
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct Unset;
+
+    /// The state of a field that is set to a value.
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct Set<T>(pub T);
+
+    /// The state of a field of a builder that was created from an existing instance, which -
+    /// unlike the state of fields that were set with their setters - allows setting it again.
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct Prefilled<T>(pub T);
+
+    /// Implemented by all the states of a field, for resolving the field to its value or - when it
+    /// is not set - to its default.
+    pub trait Optional<T> {
+        /// The value of the field, or the result of `default` if the field is not set.
+        fn into_value<F: FnOnce() -> T>(self, default: F) -> T;
+    }
+
+    impl<T> Optional<T> for Unset {
+        fn into_value<F: FnOnce() -> T>(self, default: F) -> T {
+            default()
+        }
+    }
+
+    impl<T> Optional<T> for Set<T> {
+        fn into_value<F: FnOnce() -> T>(self, _: F) -> T {
+            self.0
+        }
+    }
+
+    impl<T> Optional<T> for Prefilled<T> {
+        fn into_value<F: FnOnce() -> T>(self, _: F) -> T {
+            self.0
+        }
+    }
+}
+
+// Where the trait was before the states of the fields got their own module.
+#[doc(hidden)]
+pub use state::Optional;
+
 #[doc(hidden)]
-pub trait Optional<T> {
-    fn into_value<F: FnOnce() -> T>(self, default: F) -> T;
+pub trait RequiredField<T, Missing> {
+    /// `Rest` with `Missing` prepended when the field is not set, and `Rest` when it is.
+    type Report<Rest: MissingFieldList>: MissingFieldList;
+
+    fn into_value(self) -> T;
+}
+
+impl<T, Missing: MissingFieldList> RequiredField<T, Missing> for state::Unset {
+    type Report<Rest: MissingFieldList> = Rest::With<Missing>;
+
+    fn into_value(self) -> T {
+        unreachable!("the build method cannot be called before all the required fields are set")
+    }
 }
 
-impl<T> Optional<T> for () {
-    fn into_value<F: FnOnce() -> T>(self, default: F) -> T {
-        default()
+impl<T, Missing> RequiredField<T, Missing> for state::Set<T> {
+    type Report<Rest: MissingFieldList> = Rest;
+
+    fn into_value(self) -> T {
+        self.0
     }
 }
 
-impl<T> Optional<T> for (T,) {
-    fn into_value<F: FnOnce() -> T>(self, _: F) -> T {
+impl<T, Missing> RequiredField<T, Missing> for state::Prefilled<T> {
+    type Report<Rest: MissingFieldList> = Rest;
+
+    fn into_value(self) -> T {
         self.0
     }
 }
 
+/// A list of the types that name the missing required fields of a builder, built from the last
+/// field to the first. A single field is listed as is, and several fields as a tuple - so that the
+/// error of the build method shows nothing but their names.
+#[doc(hidden)]
+pub trait MissingFieldList {
+    type With<Missing: MissingFieldList>: MissingFieldList;
+}
+
+impl MissingFieldList for () {
+    type With<Missing: MissingFieldList> = Missing;
+}
+
+macro_rules! impl_missing_field_list {
+    (@flat $last:ident) => {};
+    (@flat $first:ident, $($rest:ident),+) => {
+        impl<$first, $($rest),+> MissingFieldList for ($first, $($rest,)+) {
+            type With<Missing: MissingFieldList> = (Missing, $first, $($rest,)+);
+        }
+
+        impl_missing_field_list!(@flat $($rest),+);
+    };
+    ($first:ident, $($rest:ident),+) => {
+        // Longer lists continue in a nested tuple.
+        impl<$first, $($rest),+> MissingFieldList for ($first, $($rest,)+) {
+            type With<Missing: MissingFieldList> = (Missing, ($first, $($rest,)+));
+        }
+
+        impl_missing_field_list!(@flat $($rest),+);
+    };
+}
+
+impl_missing_field_list!(
+    T0, T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12, T13, T14, T15, T16, T17, T18, T19, T20, T21, T22, T23, T24, T25, T26,
+    T27, T28, T29, T30, T31
+);
+
+/// The error of building a runtime-checked builder - see `runtime` in [`TypedBuilder`] - before
+/// all of its required fields are set.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct MissingFields {
+    builder_name: &'static str,
+    fields: &'static [&'static str],
+    missing: u128,
+}
+
+impl MissingFields {
+    #[doc(hidden)]
+    pub const fn new(builder_name: &'static str, fields: &'static [&'static str], missing: u128) -> Self {
+        Self {
+            builder_name,
+            fields,
+            missing,
+        }
+    }
+
+    /// The name of the builder type that was built.
+    pub const fn builder_name(&self) -> &'static str {
+        self.builder_name
+    }
+
+    /// The names of the required fields that are not set, in the order they are declared in.
+    pub fn fields(&self) -> impl Iterator<Item = &'static str> {
+        let missing = self.missing;
+        self.fields
+            .iter()
+            .enumerate()
+            .filter(move |(i, _)| missing & (1 << i) != 0)
+            .map(|(_, field)| *field)
+    }
+}
+
+impl core::fmt::Display for MissingFields {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "missing required fields on `{}`: ", self.builder_name)?;
+        for (i, field) in self.fields().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "`{}`", field)?;
+        }
+        Ok(())
+    }
+}
+
+impl core::fmt::Debug for MissingFields {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        struct Fields<'a>(&'a MissingFields);
+
+        impl core::fmt::Debug for Fields<'_> {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                f.debug_list().entries(self.0.fields()).finish()
+            }
+        }
+
+        f.debug_struct("MissingFields")
+            .field("builder_name", &self.builder_name)
+            .field("fields", &Fields(self))
+            .finish()
+    }
+}
+
+/// A type with a builder, for code that is generic over the types that derive [`TypedBuilder`].
+///
+/// Implemented for structs that derive `TypedBuilder`, unless the visibility of their builder
+/// method or builder type is changed - the implementation would expose them with the visibility of
+/// the trait.
+///
+/// ```
+/// use typed_builder::{Build, HasBuilder, TypedBuilder};
+///
+/// #[derive(Debug, PartialEq, TypedBuilder)]
+/// struct Foo {
+///     #[builder(default = 1)]
+///     x: i32,
+/// }
+///
+/// fn default_of<T>() -> T
+/// where
+///     T: HasBuilder,
+///     T::Builder: Build<Output = T>,
+/// {
+///     T::builder().build()
+/// }
+///
+/// assert_eq!(default_of::<Foo>(), Foo { x: 1 });
+/// ```
+pub trait HasBuilder {
+    /// The builder in its initial state.
+    type Builder;
+
+    /// Create the builder - same as the builder method of the type.
+    fn builder() -> Self::Builder;
+}
+
+#[doc(hidden)]
+pub trait IntoBuilder {
+    type Builder;
+
+    fn into_builder(self) -> Self::Builder;
+}
+
+/// A builder that can be built, for accepting any builder whose required fields are all set
+/// without naming the state of its fields.
+///
+/// Implemented for every builder in the states its build method can be called in. The `Output` is
+/// the return type of the build method - the type set with `build_method(into = ...)`, or a
+/// `Result` when the build method is fallible. With a generic `build_method(into)`, the `Output`
+/// is the built type itself. Builders whose build method is `async`, `unsafe`, or has its
+/// visibility changed don't implement it.
+///
+/// ```
+/// use typed_builder::{Build, TypedBuilder};
+///
+/// #[derive(Debug, PartialEq, TypedBuilder)]
+/// struct Config {
+///     name: &'static str,
+///     #[builder(default = 4)]
+///     threads: usize,
+/// }
+///
+/// fn spawn(config: impl Build<Output = Config>) -> usize {
+///     config.build().threads
+/// }
+///
+/// assert_eq!(spawn(Config::builder().name("worker")), 4);
+/// assert_eq!(spawn(Config::builder().threads(8).name("worker")), 8);
+/// ```
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` cannot be built",
+    label = "not all the required fields of this builder are set",
+    note = "a builder can only be built after the setters of all its required fields are called"
+)]
+pub trait Build {
+    /// The return type of the build method.
+    type Output;
+
+    /// Call the build method of the builder.
+    fn build(self) -> Self::Output;
+}
+
+#[doc(hidden)]
+pub trait FlattenSetter<const INDEX: usize, Args> {
+    type Output;
+
+    fn set(self, args: Args) -> Self::Output;
+}
+
+#[doc(hidden)]
+pub use typed_builder_macro::__flatten_builder;
+
 // It'd be nice for the compilation tests to live in tests/ with the rest, but short of pulling in
 // some other test runner for that purpose (e.g. compiletest_rs), rustdoc compile_fail in this
 // crate is all we can use.
@@ -332,19 +1205,602 @@ impl<T> Optional<T> for (T,) {
 /// let _ = Foo::builder().x(Uncloneable).clone();
 /// ```
 ///
-/// Handling deprecated fields:
+/// Building before all the required fields are set is an error, which lists all the missing fields:
+/// (“missing required fields on `FooBuilder`: (x, z)”)
 ///
 /// ```compile_fail
 /// use typed_builder::TypedBuilder;
 ///
 /// #[derive(TypedBuilder)]
 /// struct Foo {
-///     #[deprecated = "Don't use this!"]
-///     #[allow(dead_code)]
-///     value: i32,
+///     x: i32,
+///     y: i32,
+///     z: i32,
 /// }
 ///
-/// #[deny(deprecated)]
-/// Foo::builder().value(42).build();
-///```
+/// let _ = Foo::builder().y(1).build();
+/// ```
+///
+/// Setting a field twice is an error:
+/// (“field `x` was already set on `FooBuilder`”)
+///
+/// ```compile_fail
+/// use typed_builder::TypedBuilder;
+///
+/// #[derive(TypedBuilder)]
+/// struct Foo {
+///     x: i32,
+/// }
+///
+/// let _ = Foo::builder().x(1).x(2).build();
+/// ```
+///
+/// Only fields that are set can be taken out of the builder:
+/// (“the field is not set”)
+///
+/// ```compile_fail
+/// use typed_builder::TypedBuilder;
+///
+/// #[derive(TypedBuilder)]
+/// struct Foo {
+///     x: i32,
+///     y: i32,
+/// }
+///
+/// let _ = Foo::builder().x(1).take_y();
+/// ```
+///
+/// Getters are only available for fields that are set:
+/// (“the field is not set”)
+///
+/// ```compile_fail
+/// use typed_builder::TypedBuilder;
+///
+/// #[derive(TypedBuilder)]
+/// struct Foo {
+///     #[builder(setter(getter))]
+///     x: i32,
+/// }
+///
+/// let _ = Foo::builder().get_x();
+/// ```
+///
+/// Unless it's overridable - but that does not apply to the other fields:
+/// (“field `y` was already set on `FooBuilder`”)
+///
+/// ```compile_fail
+/// use typed_builder::TypedBuilder;
+///
+/// #[derive(TypedBuilder)]
+/// struct Foo {
+///     #[builder(setter(overridable))]
+///     x: i32,
+///     y: i32,
+/// }
+///
+/// let _ = Foo::builder().x(1).y(2).x(3).y(4).build();
+/// ```
+///
+/// The closure of a nested setter must set all the required fields of the inner builder:
+/// (“missing required fields on `InnerBuilder`: (x,)”)
+///
+/// ```compile_fail
+/// use typed_builder::TypedBuilder;
+///
+/// #[derive(TypedBuilder)]
+/// struct Inner {
+///     x: i32,
+///     #[builder(default)]
+///     y: i32,
+/// }
+///
+/// #[derive(TypedBuilder)]
+/// struct Outer {
+///     #[builder(setter(nested))]
+///     inner: Inner,
+/// }
+///
+/// let _ = Outer::builder().inner(|b| b.y(2)).build();
+/// ```
+///
+/// The setters of a flattened field cannot clash with the other setters:
+/// (“the setter `retries` of the flattened field `net` clashes with the setter of `retries`”)
+///
+/// ```compile_fail
+/// use typed_builder::TypedBuilder;
+///
+/// #[derive(TypedBuilder)]
+/// #[builder(flattenable)]
+/// struct Net {
+///     retries: u8,
+/// }
+///
+/// #[derive(TypedBuilder)]
+/// struct Foo {
+///     #[builder(flatten)]
+///     net: Net,
+///     retries: u8,
+/// }
+/// ```
+///
+/// Handling deprecated fields:
+///
+/// ```compile_fail
+/// use typed_builder::TypedBuilder;
+///
+/// #[derive(TypedBuilder)]
+/// struct Foo {
+///     #[deprecated = "Don't use this!"]
+///     #[allow(dead_code)]
+///     value: i32,
+/// }
+///
+/// #[deny(deprecated)]
+/// Foo::builder().value(42).build();
+///```
+///
+/// `validate` without an `error` type is disallowed:
+///
+/// ```compile_fail
+/// use typed_builder::TypedBuilder;
+///
+/// #[derive(TypedBuilder)]
+/// struct Foo {
+///     #[builder(validate = |x: &i32| if *x < 0 { Err(()) } else { Ok(()) })]
+///     x: i32,
+/// }
+/// ```
+///
+/// `validate` needs a function:
+///
+/// ```compile_fail
+/// use typed_builder::TypedBuilder;
+///
+/// #[derive(TypedBuilder)]
+/// #[builder(build_method(error = ()))]
+/// struct Foo {
+///     #[builder(validate)]
+///     x: i32,
+/// }
+/// ```
+///
+/// `error` needs a type:
+///
+/// ```compile_fail
+/// use typed_builder::TypedBuilder;
+///
+/// #[derive(TypedBuilder)]
+/// #[builder(build_method(validate = |_: &Foo| Ok(()), error))]
+/// struct Foo {
+///     x: i32,
+/// }
+/// ```
+///
+/// A fallible build method cannot use a generic `into`:
+///
+/// ```compile_fail
+/// use typed_builder::TypedBuilder;
+///
+/// #[derive(TypedBuilder)]
+/// #[builder(build_method(into, error = ()))]
+/// struct Foo {
+///     x: i32,
+/// }
+/// ```
+///
+/// `setter(name = ...)` cannot be shared by all the fields:
+///
+/// ```compile_fail
+/// use typed_builder::TypedBuilder;
+///
+/// #[derive(TypedBuilder)]
+/// #[builder(field_defaults(setter(name = value)))]
+/// struct Foo {
+///     x: i32,
+/// }
+/// ```
+///
+/// Naming the builder type of an enum is only allowed on its variants:
+///
+/// ```compile_fail
+/// use typed_builder::TypedBuilder;
+///
+/// #[derive(TypedBuilder)]
+/// #[builder(builder_type(name = ShapeBuilder))]
+/// enum Shape {
+///     Circle { radius: f64 },
+/// }
+/// ```
+///
+/// `builder_fn` does not support `impl Trait` arguments:
+///
+/// ```compile_fail
+/// #[typed_builder::builder_fn]
+/// fn show(value: impl std::fmt::Display) -> String {
+///     value.to_string()
+/// }
+/// ```
+///
+/// Methods with a receiver need `builder_impl` on their `impl` block:
+///
+/// ```compile_fail
+/// struct Foo;
+///
+/// impl Foo {
+///     #[typed_builder::builder_fn]
+///     fn bar(&self, x: i32) -> i32 {
+///         x
+///     }
+/// }
+/// ```
+///
+/// `builder_impl` cannot be used on trait implementations:
+///
+/// ```compile_fail
+/// struct Foo;
+///
+/// #[typed_builder::builder_impl]
+/// impl Default for Foo {
+///     #[builder_fn]
+///     fn default() -> Self {
+///         Foo
+///     }
+/// }
+/// ```
+///
+/// `flattenable` needs the builder to be usable from other builders:
+///
+/// ```compile_fail
+/// use typed_builder::TypedBuilder;
+///
+/// #[derive(TypedBuilder)]
+/// #[builder(flattenable, builder_method(vis = ""))]
+/// struct Net {
+///     retries: u8,
+/// }
+/// ```
+///
+/// Fields can only be taken out of the builder where they are visible:
+///
+/// ```compile_fail
+/// mod bank {
+///     use typed_builder::TypedBuilder;
+///
+///     #[derive(TypedBuilder)]
+///     #[builder(into_builder)]
+///     pub struct Account {
+///         pub name: String,
+///         secret: u64,
+///     }
+///
+///     impl Account {
+///         pub fn new(name: String) -> Self {
+///             Self::builder().name(name).secret(42).build()
+///         }
+///     }
+/// }
+///
+/// let (secret, _) = bank::Account::new("x".to_owned()).into_builder().take_secret();
+/// ```
+///
+/// The receiver of a method has no getter, even though it's always set:
+///
+/// ```compile_fail
+/// struct Counter(u32);
+///
+/// #[typed_builder::builder_impl]
+/// impl Counter {
+///     #[builder_fn]
+///     fn add(&mut self, #[builder(default = 1)] n: u32) {
+///         self.0 += n;
+///     }
+/// }
+///
+/// let mut counter = Counter(0);
+/// let builder = counter.add();
+/// builder.get___receiver();
+/// ```
+///
+/// `XIsUnset` is only implemented while the field is not set, even when it can be set again:
+///
+/// ```compile_fail
+/// use typed_builder::TypedBuilder;
+///
+/// #[derive(TypedBuilder)]
+/// struct Foo {
+///     #[builder(setter(overridable))]
+///     x: i32,
+/// }
+///
+/// fn x_is_unset<S: foo_builder::XIsUnset>(_: FooBuilder<S>) {}
+///
+/// x_is_unset(Foo::builder().x(1));
+/// ```
+///
+/// `into_builder` is only supported for structs:
+///
+/// ```compile_fail
+/// use typed_builder::TypedBuilder;
+///
+/// #[derive(TypedBuilder)]
+/// enum Shape {
+///     #[builder(into_builder)]
+///     Circle { radius: f64 },
+/// }
+/// ```
+///
+/// `builder_type(stable)` does not support flattened fields:
+///
+/// ```compile_fail
+/// use typed_builder::TypedBuilder;
+///
+/// #[derive(TypedBuilder)]
+/// #[builder(flattenable)]
+/// struct Net {
+///     retries: u8,
+/// }
+///
+/// #[derive(TypedBuilder)]
+/// #[builder(builder_type(stable))]
+/// struct Foo {
+///     #[builder(flatten)]
+///     net: Net,
+/// }
+/// ```
+///
+/// A builder whose required fields are not all set doesn't implement `Build`:
+/// (“`FooBuilder<(Unset,)>` cannot be built”)
+///
+/// ```compile_fail
+/// use typed_builder::{Build, TypedBuilder};
+///
+/// #[derive(TypedBuilder)]
+/// struct Foo {
+///     x: i32,
+/// }
+///
+/// fn build(builder: impl Build<Output = Foo>) -> Foo {
+///     builder.build()
+/// }
+///
+/// let _ = build(Foo::builder());
+/// ```
+///
+/// With `ordered`, the required fields must be set in the order they are declared in:
+/// (“no method named `y` found for struct `FooBuilder`”)
+///
+/// ```compile_fail
+/// use typed_builder::TypedBuilder;
+///
+/// #[derive(TypedBuilder)]
+/// #[builder(ordered)]
+/// struct Foo {
+///     x: i32,
+///     y: i32,
+/// }
+///
+/// let _ = Foo::builder().y(1);
+/// ```
+///
+/// With `ordered`, the fields with defaults can only be set after all the required fields:
+///
+/// ```compile_fail
+/// use typed_builder::TypedBuilder;
+///
+/// #[derive(TypedBuilder)]
+/// #[builder(ordered)]
+/// struct Foo {
+///     x: i32,
+///     #[builder(default)]
+///     y: i32,
+/// }
+///
+/// let _ = Foo::builder().y(1).x(1);
+/// ```
+///
+/// With `ordered`, setting a later field up front - with the builder method or a preset - doesn't
+/// make the setters of the fields before it optional:
+///
+/// ```compile_fail
+/// use typed_builder::TypedBuilder;
+///
+/// #[derive(TypedBuilder)]
+/// #[builder(ordered, builder_method(args(y)))]
+/// struct Foo {
+///     x: i32,
+///     y: i32,
+///     #[builder(default)]
+///     z: i32,
+/// }
+///
+/// let _ = Foo::builder(1).z(1).x(1);
+/// ```
+///
+/// The defaults of fields cannot depend on each other in a cycle:
+/// (“the defaults of these fields depend on each other: `x` -> `y` -> `x`”)
+///
+/// ```compile_fail
+/// use typed_builder::TypedBuilder;
+///
+/// #[derive(TypedBuilder)]
+/// struct Foo {
+///     #[builder(default = y + 1)]
+///     x: i32,
+///     #[builder(default = x - 1)]
+///     y: i32,
+/// }
+/// ```
+///
+/// The fields that the builder method takes as arguments are already set:
+///
+/// ```compile_fail
+/// use typed_builder::TypedBuilder;
+///
+/// #[derive(TypedBuilder)]
+/// #[builder(builder_method(args(x)))]
+/// struct Foo {
+///     x: i32,
+/// }
+///
+/// let _ = Foo::builder(1).x(2);
+/// ```
+///
+/// Only fields with setters can be arguments of the builder method:
+/// (“only fields with setters can be arguments of the builder method”)
+///
+/// ```compile_fail
+/// use typed_builder::TypedBuilder;
+///
+/// #[derive(TypedBuilder)]
+/// #[builder(builder_method(args(x)))]
+/// struct Foo {
+///     #[builder(default, setter(skip))]
+///     x: i32,
+/// }
+/// ```
+///
+/// The fields that the builder method takes as arguments can't be `each`:
+/// (“fields whose setter is `nested`, `each` or `strip_bool` cannot be arguments of the builder method”)
+///
+/// ```compile_fail
+/// use typed_builder::TypedBuilder;
+///
+/// #[derive(TypedBuilder)]
+/// #[builder(builder_method(args(x)))]
+/// struct Foo {
+///     #[builder(setter(each = "item"))]
+///     x: Vec<i32>,
+/// }
+/// ```
+///
+/// Presets can only set fields that have setters:
+/// (“only fields with setters can be set by presets”)
+///
+/// ```compile_fail
+/// use typed_builder::TypedBuilder;
+///
+/// #[derive(TypedBuilder)]
+/// #[builder(presets(dev(x = 1)))]
+/// struct Foo {
+///     #[builder(default, setter(skip))]
+///     x: i32,
+/// }
+/// ```
+///
+/// A mutator that sets fields can only be called while they can be set:
+///
+/// ```compile_fail
+/// use typed_builder::TypedBuilder;
+///
+/// #[derive(TypedBuilder)]
+/// #[builder(mutators(
+///     #[mutator(sets = [x])]
+///     fn init_x(&mut self) {
+///         self.x = 1;
+///     }
+/// ))]
+/// struct Foo {
+///     x: i32,
+/// }
+///
+/// let _ = Foo::builder().x(2).init_x();
+/// ```
+///
+/// After a mutator sets a field, its setter cannot be called:
+///
+/// ```compile_fail
+/// use typed_builder::TypedBuilder;
+///
+/// #[derive(TypedBuilder)]
+/// #[builder(mutators(
+///     #[mutator(sets = [x])]
+///     fn init_x(&mut self) {
+///         self.x = 1;
+///     }
+/// ))]
+/// struct Foo {
+///     x: i32,
+/// }
+///
+/// let _ = Foo::builder().init_x().x(2);
+/// ```
+///
+/// With `ordered`, a mutator that sets fields can only be called once their turn has come:
+///
+/// ```compile_fail
+/// use typed_builder::TypedBuilder;
+///
+/// #[derive(TypedBuilder)]
+/// #[builder(ordered, mutators(
+///     #[mutator(sets = [y])]
+///     fn init_y(&mut self) {
+///         self.y = 1;
+///     }
+/// ))]
+/// struct Foo {
+///     x: i32,
+///     y: i32,
+/// }
+///
+/// let _ = Foo::builder().init_y();
+/// ```
+///
+/// Mutators can only set fields that have setters:
+/// (“only fields with setters can be set by mutators”)
+///
+/// ```compile_fail
+/// use typed_builder::TypedBuilder;
+///
+/// #[derive(TypedBuilder)]
+/// #[builder(mutators(
+///     #[mutator(sets = [x])]
+///     fn init_x(&mut self) {
+///         self.x = 1;
+///     }
+/// ))]
+/// struct Foo {
+///     #[builder(via_mutators)]
+///     x: i32,
+/// }
+/// ```
+///
+/// Mutators can only return `()` or `Result<(), E>`:
+/// (“mutators can only return `()` or `Result<(), E>`”)
+///
+/// ```compile_fail
+/// use typed_builder::TypedBuilder;
+///
+/// #[derive(TypedBuilder)]
+/// #[builder(mutators(
+///     fn inc_x(&mut self) -> i32 {
+///         self.x += 1;
+///         self.x
+///     }
+/// ))]
+/// struct Foo {
+///     #[builder(via_mutators)]
+///     x: i32,
+/// }
+/// ```
+///
+/// The runtime builder supports up to 128 required fields:
+/// (“the runtime builder supports up to 128 required fields”)
+///
+/// ```compile_fail
+/// use typed_builder::TypedBuilder;
+///
+/// #[derive(TypedBuilder)]
+/// #[builder(runtime)]
+/// struct Foo(
+///     i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32,
+///     i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32,
+///     i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32,
+///     i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32,
+///     i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32,
+///     i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32,
+///     i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32,
+///     i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32,
+///     i32,
+/// );
+/// ```
 fn _compile_fail_tests() {}
@@ -1,8 +1,11 @@
 #![warn(clippy::pedantic)]
 #![allow(clippy::disallowed_names, clippy::type_complexity)]
 
+use typed_builder::state::{Set, Unset};
 use typed_builder::TypedBuilder;
 
+mod util;
+
 #[test]
 fn test_simple() {
     #[derive(PartialEq, TypedBuilder)]
@@ -263,6 +266,131 @@ fn test_field_dependencies_in_build() {
     );
 }
 
+#[test]
+fn test_field_dependencies_out_of_order() {
+    #[derive(PartialEq, Debug, TypedBuilder)]
+    struct Foo {
+        #[builder(default = format!("{}:{}", host, port))]
+        address: String,
+        #[builder(default = port.checked_add(1).unwrap_or(port), setter(skip))]
+        next_port: u16,
+        #[builder(default = if host == "localhost" { 8080 } else { 80 })]
+        port: u16,
+        host: &'static str,
+    }
+
+    assert_eq!(
+        Foo::builder().host("localhost").build(),
+        Foo {
+            address: "localhost:8080".to_owned(),
+            next_port: 8081,
+            port: 8080,
+            host: "localhost",
+        }
+    );
+    assert_eq!(
+        Foo::builder().port(1).host("example.com").build(),
+        Foo {
+            address: "example.com:1".to_owned(),
+            next_port: 2,
+            port: 1,
+            host: "example.com",
+        }
+    );
+}
+
+#[test]
+fn test_field_dependencies_ignore_called_functions() {
+    fn host() -> String {
+        "localhost".to_owned()
+    }
+
+    // Neither default refers to another field, so the fields keep their order - and `host()` still
+    // calls the function rather than the field that comes after it.
+    #[derive(PartialEq, Debug, TypedBuilder)]
+    #[builder(runtime)]
+    struct Foo {
+        #[builder(default = host())]
+        url: String,
+        #[builder(default = format!("{}:80", host()))]
+        address: String,
+        host: String,
+    }
+
+    assert_eq!(
+        Foo::builder().host("example.com".to_owned()).build(),
+        Foo {
+            url: "localhost".to_owned(),
+            address: "localhost:80".to_owned(),
+            host: "example.com".to_owned(),
+        }
+    );
+    assert_eq!(
+        Foo::runtime_builder().host("example.com".to_owned()).build().unwrap().url,
+        "localhost"
+    );
+}
+
+#[test]
+fn test_field_dependencies_ignore_local_variables() {
+    // The `b` in the default of `a` is a local variable, so only `b` depends on `a`.
+    #[derive(PartialEq, Debug, TypedBuilder)]
+    struct Bar {
+        #[builder(default = {
+            let b = 1;
+            b + 1
+        })]
+        a: i32,
+        #[builder(default = a * 2)]
+        b: i32,
+    }
+
+    assert_eq!(Bar::builder().build(), Bar { a: 2, b: 4 });
+    assert_eq!(Bar::builder().a(3).build(), Bar { a: 3, b: 6 });
+}
+
+#[test]
+fn test_field_dependencies_shadowed_fields() {
+    // The fields are only shadowed where the local variables are in scope, so `a` depends on `b`
+    // and `c` depends on `a`.
+    #[derive(PartialEq, Debug, TypedBuilder)]
+    struct Foo {
+        #[builder(default = b + {
+            let b = 1;
+            b + b
+        })]
+        a: i32,
+        #[builder(default = 2)]
+        b: i32,
+        #[builder(default = [1, 2].iter().map(|a| a * 10).sum::<i32>() + a)]
+        c: i32,
+        #[builder(default = match c {
+            d if d > 100 => 0,
+            d => d + 1,
+        })]
+        d: i32,
+    }
+
+    assert_eq!(
+        Foo::builder().build(),
+        Foo {
+            a: 4,
+            b: 2,
+            c: 34,
+            d: 35
+        }
+    );
+    assert_eq!(
+        Foo::builder().b(5).build(),
+        Foo {
+            a: 7,
+            b: 5,
+            c: 37,
+            d: 38
+        }
+    );
+}
+
 // compile-fail tests for skip are in src/lib.rs out of necessity. These are just the bland
 // successful cases.
 #[test]
@@ -327,8 +455,8 @@ fn test_builder_type_stability() {
         z: i32,
     }
 
-    impl<Y> FooBuilder<((), Y, ())> {
-        fn xz(self, x: i32, z: i32) -> FooBuilder<((i32,), Y, (i32,))> {
+    impl<Y> FooBuilder<(Unset, Y, Unset)> {
+        fn xz(self, x: i32, z: i32) -> FooBuilder<(Set<i32>, Y, Set<i32>)> {
             self.x(x).z(z)
         }
     }
@@ -348,8 +476,8 @@ fn test_builder_type_stability_with_other_generics() {
         y: Y,
     }
 
-    impl<X: Default, Y, Y_> FooBuilder<X, Y, ((), Y_)> {
-        fn x_default(self) -> FooBuilder<X, Y, ((X,), Y_)> {
+    impl<X: Default, Y, Y_> FooBuilder<X, Y, (Unset, Y_)> {
+        fn x_default(self) -> FooBuilder<X, Y, (Set<X>, Y_)> {
             self.x(X::default())
         }
     }
@@ -391,14 +519,14 @@ fn test_builder_type_with_default_on_generic_type() {
         m: M,
     }
 
-    impl<'a, X, Y: Default, M, X_, Y_, M_> FooBuilder<'a, X, Y, usize, M, (X_, Y_, (), M_)> {
-        fn z_default(self) -> FooBuilder<'a, X, Y, usize, M, (X_, Y_, (usize,), M_)> {
+    impl<'a, X, Y: Default, M, X_, Y_, M_> FooBuilder<'a, X, Y, usize, M, (X_, Y_, Unset, M_)> {
+        fn z_default(self) -> FooBuilder<'a, X, Y, usize, M, (X_, Y_, Set<usize>, M_)> {
             self.z(usize::default())
         }
     }
 
-    impl<'a, X, Y: Default, Z: Default, X_, Y_, Z_> FooBuilder<'a, X, Y, Z, (), (X_, Y_, Z_, ())> {
-        fn m_default(self) -> FooBuilder<'a, X, Y, Z, (), (X_, Y_, Z_, ((),))> {
+    impl<'a, X, Y: Default, Z: Default, X_, Y_, Z_> FooBuilder<'a, X, Y, Z, (), (X_, Y_, Z_, Unset)> {
+        fn m_default(self) -> FooBuilder<'a, X, Y, Z, (), (X_, Y_, Z_, Set<()>)> {
             self.m(())
         }
     }
@@ -531,6 +659,7 @@ fn test_clone_builder() {
 #[test]
 #[allow(clippy::items_after_statements)]
 fn test_clone_builder_with_generics() {
+    #[allow(dead_code)]
     #[derive(PartialEq, Default)]
     struct Uncloneable;
 
@@ -922,3 +1051,1621 @@ fn test_mutators_field() {
     let foo = Foo::builder().x(1).y(1).inc_y_by_x().build();
     assert_eq!(foo, Foo { x: 1, y: 2, z: 2, w: 2 });
 }
+
+#[test]
+fn test_mutators_generics() {
+    #[derive(Debug, PartialEq, TypedBuilder)]
+    #[builder(mutators(
+        #[mutator(requires = [x])]
+        fn push_x(self, item: T) {
+            self.x.push(item);
+        }
+        fn count(self) {
+            self.count += 1;
+        }
+    ))]
+    struct Foo<S: Default, T: Clone + Default = i32> {
+        x: Vec<T>,
+        #[builder(default)]
+        y: S,
+        #[builder(via_mutators)]
+        count: usize,
+    }
+
+    assert_eq!(
+        Foo::builder().x(vec![1]).push_x(2).count().y("a").build(),
+        Foo {
+            x: vec![1, 2],
+            y: "a",
+            count: 1,
+        }
+    );
+}
+
+#[test]
+fn test_mutators_lifetime() {
+    #[derive(Debug, PartialEq, TypedBuilder)]
+    struct Foo<'a, 'b: 'a> {
+        #[builder(mutators(
+            fn trim(self) {
+                self.x = self.x.trim();
+            }
+            #[mutator(requires = [y])]
+            fn pick_y(self) {
+                self.x = self.y;
+            }
+        ))]
+        x: &'a str,
+        y: &'b str,
+    }
+
+    assert_eq!(Foo::builder().x(" a ").trim().y("b").build(), Foo { x: "a", y: "b" });
+    assert_eq!(Foo::builder().x("a").y("b").pick_y().build(), Foo { x: "b", y: "b" });
+}
+
+#[test]
+fn test_mutators_const_generics() {
+    #[derive(Debug, PartialEq, TypedBuilder)]
+    #[builder(mutators(
+        fn fill(self, value: u32) {
+            self.data = [value; N];
+        }
+    ))]
+    struct Foo<const N: usize> {
+        #[builder(via_mutators(init = [0; N]))]
+        data: [u32; N],
+        #[builder(default)]
+        unrelated: u8,
+    }
+
+    assert_eq!(
+        Foo::<3>::builder().fill(1).build(),
+        Foo {
+            data: [1; 3],
+            unrelated: 0
+        }
+    );
+}
+
+#[test]
+fn test_mutators_sets() {
+    #[derive(Debug, PartialEq, TypedBuilder)]
+    #[builder(mutators(
+        #[mutator(sets = [host, port])]
+        fn address(self, address: &str) {
+            let (host, port) = address.split_once(':').unwrap();
+            self.host = host.to_owned();
+            self.port = port.parse().unwrap();
+        }
+        #[mutator(requires = [scheme], sets = [path])]
+        fn root(self) {
+            self.path = format!("{}:///", self.scheme);
+        }
+    ))]
+    struct Url {
+        scheme: &'static str,
+        host: String,
+        #[builder(default = 80)]
+        port: u16,
+        #[builder(default)]
+        path: String,
+    }
+
+    assert_eq!(
+        Url::builder().scheme("http").address("localhost:8080").build(),
+        Url {
+            scheme: "http",
+            host: "localhost".to_owned(),
+            port: 8080,
+            path: String::new(),
+        }
+    );
+    assert_eq!(
+        Url::builder()
+            .address("example.com:443")
+            .path("/a".to_owned())
+            .scheme("https")
+            .build(),
+        Url {
+            scheme: "https",
+            host: "example.com".to_owned(),
+            port: 443,
+            path: "/a".to_owned(),
+        }
+    );
+    assert_eq!(
+        Url::builder().scheme("file").root().host("h".to_owned()).build(),
+        Url {
+            scheme: "file",
+            host: "h".to_owned(),
+            port: 80,
+            path: "file:///".to_owned(),
+        }
+    );
+}
+
+#[test]
+fn test_mutators_sets_settable_states() {
+    #[derive(Debug, PartialEq, TypedBuilder)]
+    #[builder(
+        ordered,
+        into_builder,
+        presets(local(host = "localhost".to_owned())),
+        mutators(
+            #[mutator(sets = [host, port])]
+            fn address(self, address: &str) {
+                let (host, port) = address.split_once(':').unwrap();
+                self.host = host.to_owned();
+                self.port = port.parse().unwrap();
+            }
+        )
+    )]
+    struct Server {
+        name: &'static str,
+        host: String,
+        #[builder(default = 80)]
+        port: u16,
+    }
+
+    let expected = Server {
+        name: "a",
+        host: "example.com".to_owned(),
+        port: 443,
+    };
+    assert_eq!(Server::builder().name("a").address("example.com:443").build(), expected);
+    assert_eq!(Server::local_builder().name("a").address("example.com:443").build(), expected);
+    let server = Server::builder().name("a").host("h".to_owned()).build();
+    assert_eq!(server.into_builder().address("example.com:443").build(), expected);
+}
+
+#[test]
+fn test_mutators_sets_unassigned() {
+    #[derive(Debug, PartialEq, TypedBuilder)]
+    #[builder(
+        into_builder,
+        mutators(
+            #[mutator(sets = [host, port])]
+            fn rename(self, host: &str) {
+                self.host = host.to_owned();
+            }
+        )
+    )]
+    struct Server {
+        host: String,
+        #[builder(default = 80)]
+        port: u16,
+    }
+
+    // A field the mutator doesn't assign keeps its previous value, or its type's default.
+    let server = Server::builder().rename("localhost").build();
+    assert_eq!(
+        server,
+        Server {
+            host: "localhost".to_owned(),
+            port: 0
+        }
+    );
+    let server = Server::builder().host("a".to_owned()).port(8080).build();
+    assert_eq!(
+        server.into_builder().rename("b").build(),
+        Server {
+            host: "b".to_owned(),
+            port: 8080
+        }
+    );
+}
+
+#[test]
+fn test_mutators_fallible() {
+    #[derive(Debug, PartialEq)]
+    enum Error {
+        MissingPort,
+        InvalidPort(std::num::ParseIntError),
+    }
+
+    #[derive(Debug, PartialEq, TypedBuilder)]
+    #[builder(mutators(
+        #[mutator(sets = [host, port])]
+        fn address(self, address: &str) -> Result<(), Error> {
+            let (host, port) = address.split_once(':').ok_or(Error::MissingPort)?;
+            self.host = host.to_owned();
+            self.port = port.parse().map_err(Error::InvalidPort)?;
+            Ok(())
+        }
+        fn retry(self) -> core::result::Result<(), String> {
+            if self.retries == 3 {
+                return Err("too many retries".to_owned());
+            }
+            self.retries += 1;
+            Ok(())
+        }
+    ))]
+    struct Server {
+        host: String,
+        port: u16,
+        #[builder(via_mutators)]
+        retries: u8,
+    }
+
+    fn build(address: &str) -> Result<Server, Error> {
+        Ok(Server::builder().address(address)?.build())
+    }
+
+    assert_eq!(
+        build("localhost:8080"),
+        Ok(Server {
+            host: "localhost".to_owned(),
+            port: 8080,
+            retries: 0,
+        })
+    );
+    assert_eq!(build("localhost"), Err(Error::MissingPort));
+    assert!(matches!(build("localhost:x"), Err(Error::InvalidPort(_))));
+
+    let builder = Server::builder().host("h".to_owned()).port(1);
+    assert_eq!(builder.retry().unwrap().retry().unwrap().build().retries, 2);
+    assert_eq!(
+        Server::builder()
+            .retry()
+            .and_then(ServerBuilder::retry)
+            .and_then(ServerBuilder::retry)
+            .and_then(ServerBuilder::retry)
+            .map(|_| ()),
+        Err("too many retries".to_owned())
+    );
+}
+
+#[test]
+fn test_mutators_fallible_result_alias() {
+    #[derive(Debug, PartialEq, TypedBuilder)]
+    #[builder(mutators(
+        #[mutator(sets = [port])]
+        fn parse_port(self, port: &str) -> std::io::Result<()> {
+            let port = port
+                .parse()
+                .map_err(|_| std::io::Error::new(std::io::ErrorKind::InvalidInput, "invalid port"))?;
+            self.port = port;
+            Ok(())
+        }
+    ))]
+    struct Server {
+        port: u16,
+    }
+
+    fn build(port: &str) -> std::io::Result<Server> {
+        Ok(Server::builder().parse_port(port)?.build())
+    }
+
+    assert_eq!(build("8080").unwrap(), Server { port: 8080 });
+    assert_eq!(build("http").unwrap_err().kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn test_field_validators() {
+    #[derive(Debug, PartialEq)]
+    enum Error {
+        Negative,
+        Empty,
+    }
+
+    #[allow(clippy::trivially_copy_pass_by_ref)]
+    fn non_negative(value: &i32) -> Result<(), Error> {
+        if *value < 0 {
+            Err(Error::Negative)
+        } else {
+            Ok(())
+        }
+    }
+
+    #[derive(Debug, PartialEq, TypedBuilder)]
+    #[builder(build_method(error = Error))]
+    struct Foo {
+        #[builder(validate = non_negative)]
+        x: i32,
+        #[builder(default = x - 1, validate = non_negative)]
+        y: i32,
+        #[builder(default, setter(into), validate = |s: &String| if s.is_empty() { Err(Error::Empty) } else { Ok(()) })]
+        z: String,
+    }
+
+    assert_eq!(
+        Foo::builder().x(1).z("z").try_build(),
+        Ok(Foo {
+            x: 1,
+            y: 0,
+            z: "z".to_owned()
+        })
+    );
+    assert_eq!(Foo::builder().x(-1).y(1).z("z").try_build(), Err(Error::Negative));
+    // Defaulted values are validated too
+    assert_eq!(Foo::builder().x(0).z("z").try_build(), Err(Error::Negative));
+    assert_eq!(Foo::builder().x(1).try_build(), Err(Error::Empty));
+}
+
+#[test]
+fn test_build_method_validator() {
+    #[derive(Debug, PartialEq)]
+    struct Error(&'static str);
+
+    impl From<()> for Error {
+        fn from((): ()) -> Self {
+            Self("field")
+        }
+    }
+
+    #[derive(Debug, PartialEq, TypedBuilder)]
+    #[builder(build_method(name = build, validate = Foo::validate, error = Error))]
+    struct Foo {
+        #[builder(validate = |x: &i32| if *x == 0 { Err(()) } else { Ok(()) })]
+        x: i32,
+        #[builder(default = 2)]
+        y: i32,
+    }
+
+    impl Foo {
+        fn validate(&self) -> Result<(), Error> {
+            if self.x < self.y {
+                Ok(())
+            } else {
+                Err(Error("x < y"))
+            }
+        }
+    }
+
+    assert_eq!(Foo::builder().x(1).build(), Ok(Foo { x: 1, y: 2 }));
+    assert_eq!(Foo::builder().x(3).build(), Err(Error("x < y")));
+    assert_eq!(Foo::builder().x(0).build(), Err(Error("field")));
+}
+
+#[test]
+fn test_validator_with_into() {
+    #[derive(Debug, PartialEq)]
+    struct Bar(i32);
+
+    #[derive(Debug, PartialEq, TypedBuilder)]
+    #[builder(build_method(into = Bar, validate = |foo: &Foo| if foo.x > 0 { Ok(()) } else { Err("x") }, error = &'static str))]
+    struct Foo {
+        x: i32,
+    }
+
+    impl From<Foo> for Bar {
+        fn from(foo: Foo) -> Self {
+            Self(foo.x)
+        }
+    }
+
+    assert_eq!(Foo::builder().x(1).try_build(), Ok(Bar(1)));
+    assert_eq!(Foo::builder().x(0).try_build(), Err("x"));
+}
+
+#[test]
+fn test_tuple_struct() {
+    #[derive(Debug, PartialEq, TypedBuilder)]
+    struct Foo(i32, #[builder(default = _0 + 1)] i32, #[builder(setter(into))] String);
+
+    assert_eq!(Foo::builder()._0(1)._2("two").build(), Foo(1, 2, "two".to_owned()));
+    assert_eq!(Foo::builder()._2("three")._1(5)._0(4).build(), Foo(4, 5, "three".to_owned()));
+}
+
+#[test]
+fn test_tuple_struct_named_setters() {
+    #[derive(Debug, PartialEq, TypedBuilder)]
+    #[builder(field_defaults(setter(prefix = "with_")))]
+    struct Foo<T>(
+        #[builder(setter(name = host, into))] String,
+        #[builder(setter(name = port), default = 80)] u16,
+        #[builder(default = format!("{host}:{port}"))] String,
+        #[builder(setter(name = extra, strip_option), default)] Option<T>,
+    );
+
+    assert_eq!(
+        Foo::builder().with_host("localhost").with_extra(1).build(),
+        Foo("localhost".to_owned(), 80, "localhost:80".to_owned(), Some(1))
+    );
+    assert_eq!(
+        Foo::<()>::builder().with_port(8080).with_host("example.com").build(),
+        Foo("example.com".to_owned(), 8080, "example.com:8080".to_owned(), None)
+    );
+}
+
+#[test]
+fn test_tuple_struct_mutators() {
+    #[derive(Debug, PartialEq, TypedBuilder)]
+    #[builder(mutators(
+        #[mutator(requires = [count])]
+        fn increment(&mut self) {
+            self.count += 1;
+        }
+    ))]
+    struct Foo(#[builder(setter(name = count))] u32, #[builder(via_mutators)] Vec<u32>);
+
+    assert_eq!(Foo::builder().count(1).increment().increment().build(), Foo(3, Vec::new()));
+}
+
+#[test]
+fn test_enum_variant_builders() {
+    #[derive(Debug, PartialEq, TypedBuilder)]
+    enum Shape {
+        Circle {
+            radius: f64,
+        },
+        #[builder(field_defaults(default))]
+        Rect {
+            width: f64,
+            #[builder(default = width)]
+            height: f64,
+        },
+        Polygon(#[builder(setter(name = points, into))] Vec<(f64, f64)>),
+        #[allow(dead_code)]
+        Empty,
+    }
+
+    assert_eq!(Shape::circle_builder().radius(1.0).build(), Shape::Circle { radius: 1.0 });
+    assert_eq!(
+        Shape::rect_builder().width(2.0).build(),
+        Shape::Rect { width: 2.0, height: 2.0 }
+    );
+    assert_eq!(Shape::rect_builder().build(), Shape::Rect { width: 0.0, height: 0.0 });
+    assert_eq!(
+        Shape::polygon_builder().points([(0.0, 0.0)]).build(),
+        Shape::Polygon(vec![(0.0, 0.0)])
+    );
+
+    let _: ShapeCircleBuilder<_> = Shape::circle_builder();
+}
+
+#[test]
+fn test_enum_variant_builder_customization() {
+    #[derive(Debug, PartialEq, TypedBuilder)]
+    #[builder(field_defaults(setter(into)))]
+    enum Message<T> {
+        #[builder(builder_method(name = text), builder_type(name = TextBuilder))]
+        Text {
+            body: String,
+            #[builder(default)]
+            attachment: Option<T>,
+        },
+        #[builder(build_method(name = finish))]
+        HttpRequest { url: String },
+    }
+
+    assert_eq!(
+        Message::<()>::text().body("hello").build(),
+        Message::Text {
+            body: "hello".to_owned(),
+            attachment: None
+        }
+    );
+    let _: TextBuilder<u32, _> = Message::text();
+    assert_eq!(
+        Message::<()>::http_request_builder().url("https://example.com").finish(),
+        Message::HttpRequest {
+            url: "https://example.com".to_owned()
+        }
+    );
+}
+
+#[test]
+fn test_builder_fn() {
+    #[typed_builder::builder_fn]
+    #[allow(clippy::needless_pass_by_value)]
+    fn connect(#[builder(setter(into))] host: String, #[builder(default = 80)] port: u16) -> String {
+        format!("{host}:{port}")
+    }
+
+    assert_eq!(connect().host("localhost").call(), "localhost:80");
+    assert_eq!(connect().port(8080).host("localhost").call(), "localhost:8080");
+    let _: ConnectBuilder<(Set<String>, Unset)> = connect().host("localhost");
+}
+
+#[test]
+fn test_builder_fn_generics_and_lifetimes() {
+    use std::borrow::Cow;
+
+    #[typed_builder::builder_fn]
+    fn first<T: Clone, const N: usize>(items: &[T; N], fallback: Cow<'_, T>) -> T {
+        items.first().cloned().unwrap_or_else(|| fallback.into_owned())
+    }
+
+    #[typed_builder::builder_fn]
+    fn skip(text: &str, #[builder(default = 1)] count: usize) -> &str {
+        &text[count..]
+    }
+
+    assert_eq!(first().items(&[1, 2]).fallback(Cow::Owned(3)).call(), 1);
+    assert_eq!(first::<i32, 0>().items(&[]).fallback(Cow::Borrowed(&3)).call(), 3);
+
+    let text = String::from("hello");
+    let skipped = skip().text(&text).call();
+    assert_eq!(skipped, "ello");
+}
+
+#[test]
+fn test_builder_fn_customization() {
+    #[typed_builder::builder_fn(builder_type(name = SumBuilder), build_method(name = run))]
+    unsafe fn add(a: i32, b: i32) -> i32 {
+        a + b
+    }
+
+    #[typed_builder::builder_fn(build_method(error = String))]
+    fn divide(
+        a: i32,
+        #[builder(validate = |b: &i32| if *b == 0 { Err("division by zero".to_owned()) } else { Ok(()) })] b: i32,
+    ) -> i32 {
+        a / b
+    }
+
+    let builder: SumBuilder<(Set<i32>, Unset)> = add().a(1);
+    assert_eq!(unsafe { builder.b(2).run() }, 3);
+    assert_eq!(divide().a(6).b(3).try_call(), Ok(2));
+    assert_eq!(divide().a(6).b(0).try_call(), Err("division by zero".to_owned()));
+}
+
+#[test]
+fn test_builder_fn_async() {
+    use util::block_on;
+
+    #[typed_builder::builder_fn]
+    #[allow(clippy::unused_async)]
+    async fn greet(name: &str, #[builder(default = "Hello")] greeting: &str) -> String {
+        format!("{greeting}, {name}!")
+    }
+
+    assert_eq!(block_on(greet().name("World").call()), "Hello, World!");
+    assert_eq!(block_on(greet().greeting("Hi").name("World").call()), "Hi, World!");
+}
+
+#[test]
+fn test_builder_impl_constructor() {
+    #[derive(Debug, PartialEq)]
+    struct Rectangle<T> {
+        width: T,
+        height: T,
+    }
+
+    #[typed_builder::builder_impl]
+    impl<T: Copy> Rectangle<T> {
+        #[builder_fn]
+        fn new(width: T, #[builder(default = width)] height: T) -> Self {
+            Self { width, height }
+        }
+
+        #[builder_fn(builder_method(name = sized), build_method(error = &'static str))]
+        fn with_area(area: T, #[builder(default)] ratio: Option<T>) -> Self
+        where
+            T: core::ops::Div<Output = T>,
+        {
+            let ratio = ratio.unwrap_or(area);
+            Self {
+                width: area / ratio,
+                height: ratio,
+            }
+        }
+    }
+
+    let rectangle: RectangleBuilder<u32, (Set<u32>, Unset)> = Rectangle::builder().width(2);
+    assert_eq!(rectangle.build(), Rectangle { width: 2, height: 2 });
+    assert_eq!(
+        Rectangle::builder().height(3).width(2).build(),
+        Rectangle { width: 2, height: 3 }
+    );
+    let rectangle: RectangleWithAreaBuilder<u32, _> = Rectangle::sized().area(6);
+    assert_eq!(rectangle.ratio(Some(2)).try_build(), Ok(Rectangle { width: 3, height: 2 }));
+}
+
+#[test]
+fn test_builder_impl_receivers() {
+    #[derive(Debug, Default, PartialEq)]
+    struct Counter {
+        count: i32,
+    }
+
+    #[typed_builder::builder_impl]
+    impl Counter {
+        #[builder_fn]
+        fn get(&self, #[builder(default)] offset: i32) -> i32 {
+            self.count + offset
+        }
+
+        #[builder_fn(build_method(name = add))]
+        fn increment(&mut self, #[builder(default = 1)] by: i32) -> &mut Self {
+            self.count += by;
+            self
+        }
+
+        #[builder_fn]
+        fn into_count(self, multiplier: i32) -> i32 {
+            self.count * multiplier
+        }
+
+        #[builder_fn(mutators(
+            fn add(&mut self, value: i32) {
+                self.values.push(value);
+            }
+        ))]
+        fn sum(&self, #[builder(via_mutators)] values: Vec<i32>) -> i32 {
+            values.into_iter().fold(self.count, |sum, value| sum + value)
+        }
+    }
+
+    let mut counter = Counter::default();
+    counter.increment().add().increment().by(2).add();
+    assert_eq!(counter.get().call(), 3);
+    assert_eq!(counter.get().offset(-1).call(), 2);
+    assert_eq!(counter.sum().add(1).add(2).call(), 6);
+    let builder: CounterGetBuilder<'_, _> = counter.get();
+    assert_eq!(builder.call(), 3);
+    assert_eq!(counter.into_count().multiplier(2).call(), 6);
+}
+
+#[test]
+fn test_each_setters() {
+    use std::collections::{BTreeMap, HashSet};
+
+    #[derive(Debug, PartialEq, TypedBuilder)]
+    struct Foo<T: std::hash::Hash + Eq> {
+        #[builder(setter(each = "arg", into))]
+        args: Vec<String>,
+        #[builder(setter(each = "tag"))]
+        tags: HashSet<T>,
+        #[builder(setter(each = "entry", prefix = "with_"), default = BTreeMap::from([(0, 0)]))]
+        entries: BTreeMap<i32, i32>,
+        x: i32,
+    }
+
+    assert_eq!(
+        Foo::builder()
+            .arg("a")
+            .x(1)
+            .arg(String::from("b"))
+            .args(["c", "d"])
+            .tag(1)
+            .tags([2, 3])
+            .with_entries([(1, 2)])
+            .entry((3, 4))
+            .build(),
+        Foo {
+            args: vec!["a".to_owned(), "b".to_owned(), "c".to_owned(), "d".to_owned()],
+            tags: HashSet::from([1, 2, 3]),
+            entries: BTreeMap::from([(1, 2), (3, 4)]),
+            x: 1,
+        }
+    );
+    assert_eq!(
+        Foo::<u8>::builder().x(2).build(),
+        Foo {
+            args: Vec::new(),
+            tags: HashSet::new(),
+            entries: BTreeMap::from([(0, 0)]),
+            x: 2,
+        }
+    );
+    // The default is not the initial collection for the items that are added.
+    assert_eq!(
+        Foo::<u8>::builder().x(3).entry((3, 4)).build().entries,
+        BTreeMap::from([(3, 4)])
+    );
+}
+
+#[test]
+fn test_nested_setters() {
+    #[derive(PartialEq, Debug, TypedBuilder)]
+    struct Inner<T> {
+        x: T,
+        #[builder(default)]
+        y: i32,
+    }
+
+    #[derive(PartialEq, Debug, TypedBuilder)]
+    #[builder(field_defaults(setter(nested)))]
+    struct Foo {
+        inner: Inner<u8>,
+        #[builder(default, setter(strip_option))]
+        maybe_inner: Option<Inner<&'static str>>,
+        #[builder(setter(!nested))]
+        z: i32,
+    }
+
+    assert_eq!(
+        Foo::builder().inner(|b| b.x(1).y(2)).z(3).build(),
+        Foo {
+            inner: Inner { x: 1, y: 2 },
+            maybe_inner: None,
+            z: 3,
+        }
+    );
+    assert_eq!(
+        Foo::builder().z(3).maybe_inner(|b| b.x("a")).inner(|b| b.x(1)).build(),
+        Foo {
+            inner: Inner { x: 1, y: 0 },
+            maybe_inner: Some(Inner { x: "a", y: 0 }),
+            z: 3,
+        }
+    );
+}
+
+#[test]
+fn test_flatten() {
+    #[derive(PartialEq, Debug, TypedBuilder)]
+    #[builder(flattenable, field_defaults(setter(prefix = "net_")))]
+    struct Net<T> {
+        #[builder(default = 30)]
+        timeout: u32,
+        retries: u8,
+        #[builder(setter(into))]
+        host: String,
+        #[builder(setter(each = "tag"))]
+        tags: Vec<T>,
+        #[builder(setter(strip_bool))]
+        verbose: bool,
+    }
+
+    #[derive(PartialEq, Debug, TypedBuilder)]
+    #[builder(flattenable)]
+    struct Point(#[builder(setter(name = x, transform = |x: i32, scale: i32| x * scale))] i32);
+
+    #[derive(PartialEq, Debug, TypedBuilder)]
+    #[builder(flattenable)]
+    struct Foo {
+        name: &'static str,
+        #[builder(flatten)]
+        net: Net<u8>,
+        #[builder(flatten)]
+        point: Point,
+    }
+
+    #[derive(PartialEq, Debug, TypedBuilder)]
+    struct Bar {
+        #[builder(default)]
+        level: u8,
+        #[builder(flatten)]
+        foo: Foo,
+    }
+
+    assert_eq!(
+        Foo::builder()
+            .net_retries(3)
+            .name("a")
+            .net_host("b")
+            .tag(1)
+            .net_tags([2, 3])
+            .x(2, 5)
+            .net_verbose()
+            .build(),
+        Foo {
+            name: "a",
+            net: Net {
+                timeout: 30,
+                retries: 3,
+                host: "b".to_owned(),
+                tags: vec![1, 2, 3],
+                verbose: true,
+            },
+            point: Point(10),
+        }
+    );
+    assert_eq!(
+        Bar::builder()
+            .x(1, 1)
+            .net_host("c")
+            .net_timeout(5)
+            .name("d")
+            .net_retries(0)
+            .build(),
+        Bar {
+            level: 0,
+            foo: Foo {
+                name: "d",
+                net: Net {
+                    timeout: 5,
+                    retries: 0,
+                    host: "c".to_owned(),
+                    tags: Vec::new(),
+                    verbose: false,
+                },
+                point: Point(1),
+            },
+        }
+    );
+}
+
+#[test]
+fn test_flatten_with_mutators() {
+    #[derive(PartialEq, Debug, TypedBuilder)]
+    #[builder(flattenable)]
+    struct Net {
+        #[builder(default = 1)]
+        retries: u8,
+    }
+
+    // The derive passes the mutators through the macro of `Net`, and the `self` of their bodies must
+    // still refer to their receivers.
+    #[derive(PartialEq, Debug, TypedBuilder)]
+    #[builder(mutators(
+        fn double_x(&mut self) {
+            self.x *= 2;
+        }
+    ))]
+    struct Foo {
+        #[builder(via_mutators(init = 1), mutators(
+            fn add_x(&mut self, x: i32) {
+                self.x += x;
+            }
+        ))]
+        x: i32,
+        #[builder(flatten)]
+        net: Net,
+    }
+
+    assert_eq!(
+        Foo::builder().add_x(2).double_x().retries(3).build(),
+        Foo {
+            x: 6,
+            net: Net { retries: 3 }
+        }
+    );
+}
+
+#[test]
+fn test_runtime_builder() {
+    #[derive(PartialEq, Debug, TypedBuilder)]
+    #[builder(runtime)]
+    struct Foo<T: Clone> {
+        x: T,
+        #[builder(default = x.clone(), setter(into))]
+        y: T,
+        #[builder(default, setter(strip_option))]
+        z: Option<i32>,
+        #[builder(setter(transform = |a: i32, b: i32| a + b))]
+        w: i32,
+        #[builder(default, setter(each = "item"))]
+        items: Vec<i32>,
+    }
+
+    #[derive(PartialEq, Debug, TypedBuilder)]
+    #[builder(runtime, build_method(into = Result<Bar, String>))]
+    struct Bar {
+        x: i32,
+    }
+
+    impl From<Bar> for Result<Bar, String> {
+        fn from(bar: Bar) -> Self {
+            Ok(bar)
+        }
+    }
+
+    #[derive(PartialEq, Debug)]
+    enum BazError {
+        Missing(typed_builder::MissingFields),
+        Negative,
+    }
+
+    impl From<typed_builder::MissingFields> for BazError {
+        fn from(missing: typed_builder::MissingFields) -> Self {
+            Self::Missing(missing)
+        }
+    }
+
+    #[derive(PartialEq, Debug, TypedBuilder)]
+    #[builder(runtime, build_method(error = BazError))]
+    enum Baz {
+        Qux {
+            #[builder(validate = |x: &i32| if *x < 0 { Err(BazError::Negative) } else { Ok(()) })]
+            x: i32,
+        },
+    }
+
+    let mut builder = Foo::runtime_builder();
+    for i in 0..3 {
+        builder.item(i);
+    }
+    let error = builder.build().unwrap_err();
+    assert_eq!(error.builder_name(), "FooRuntimeBuilder");
+    assert_eq!(error.fields().collect::<Vec<_>>(), ["x", "w"]);
+    assert_eq!(error.to_string(), "missing required fields on `FooRuntimeBuilder`: `x`, `w`");
+
+    // A failed build keeps the values that were set.
+    builder.x("a".to_owned()).w(1, 2);
+    assert_eq!(
+        builder.build(),
+        Ok(Foo {
+            x: "a".to_owned(),
+            y: "a".to_owned(),
+            z: None,
+            w: 3,
+            items: vec![0, 1, 2],
+        })
+    );
+
+    // Setters can be called again, and the last value wins.
+    assert_eq!(
+        Foo::runtime_builder().x(1).y(2).w(0, 0).y(3).z(4).build(),
+        Ok(Foo {
+            x: 1,
+            y: 3,
+            z: Some(4),
+            w: 0,
+            items: Vec::new(),
+        })
+    );
+
+    assert_eq!(Bar::runtime_builder().x(1).build(), Ok(Ok(Bar { x: 1 })));
+
+    let mut builder = Baz::qux_runtime_builder();
+    assert!(matches!(builder.try_build(), Err(BazError::Missing(_))));
+    assert_eq!(builder.x(-1).try_build(), Err(BazError::Negative));
+    assert_eq!(builder.x(1).try_build(), Ok(Baz::Qux { x: 1 }));
+}
+
+#[test]
+fn test_into_builder() {
+    #[derive(PartialEq, Debug, Clone, TypedBuilder)]
+    #[builder(flattenable, into_builder)]
+    struct Net {
+        host: &'static str,
+        #[builder(default = 80)]
+        port: u16,
+    }
+
+    #[derive(PartialEq, Debug, Clone, TypedBuilder)]
+    #[builder(into_builder)]
+    struct Foo<T> {
+        x: T,
+        #[builder(default, setter(strip_option))]
+        y: Option<i32>,
+        #[builder(default, setter(skip))]
+        z: i32,
+        #[builder(default, setter(each = "item"))]
+        items: Vec<i32>,
+        #[builder(via_mutators(init = 1), mutators(
+            fn double_w(&mut self) {
+                self.w *= 2;
+            }
+        ))]
+        w: i32,
+        #[builder(flatten)]
+        net: Net,
+    }
+
+    mod api {
+        use typed_builder::TypedBuilder;
+
+        #[derive(PartialEq, Debug, Clone)]
+        pub(crate) struct Secret(pub(crate) i32);
+
+        // The fields are less visible than the struct, so it must not implement `IntoBuilder`.
+        #[derive(PartialEq, Debug, Clone, TypedBuilder)]
+        #[builder(into_builder)]
+        pub struct Bar {
+            pub(crate) secret: Secret,
+            #[builder(default)]
+            pub(crate) y: i32,
+        }
+    }
+
+    // Without `into_builder`, the struct is free to have methods with these names.
+    #[derive(TypedBuilder)]
+    struct Baz {
+        x: i32,
+    }
+
+    impl Baz {
+        fn to_builder(&self) -> BazBuilder<(typed_builder::state::Set<i32>,)> {
+            Baz::builder().x(self.x + 1)
+        }
+    }
+
+    let foo = Foo::builder().x("a").y(2).item(1).host("localhost").double_w().build();
+
+    // The cloning version keeps the original, and the setters replace the values.
+    assert_eq!(
+        foo.to_builder().x("b").port(8080).build(),
+        Foo {
+            x: "b",
+            y: Some(2),
+            z: 0,
+            items: vec![1],
+            w: 2,
+            net: Net {
+                host: "localhost",
+                port: 8080,
+            },
+        }
+    );
+
+    // Collections are extended, and mutators can still be used.
+    assert_eq!(
+        foo.into_builder().item(2).double_w().y(3).build(),
+        Foo {
+            x: "a",
+            y: Some(3),
+            z: 0,
+            items: vec![1, 2],
+            w: 4,
+            net: Net {
+                host: "localhost",
+                port: 80,
+            },
+        }
+    );
+
+    let bar = api::Bar::builder().secret(api::Secret(1)).build();
+    assert_eq!(
+        bar.to_builder().y(2).build(),
+        api::Bar {
+            secret: api::Secret(1),
+            y: 2
+        }
+    );
+    assert_eq!(Baz::builder().x(1).build().to_builder().build().x, 2);
+}
+
+#[test]
+fn test_overridable_setters() {
+    #[derive(PartialEq, Debug, TypedBuilder)]
+    #[builder(field_defaults(setter(overridable)))]
+    struct Foo {
+        #[builder(setter(!overridable))]
+        x: i32,
+        #[builder(default, setter(strip_option))]
+        y: Option<i32>,
+        #[builder(default, setter(each = "item"))]
+        items: Vec<i32>,
+    }
+
+    #[derive(PartialEq, Debug, TypedBuilder)]
+    struct Bar {
+        #[builder(setter(overridable, strip_bool))]
+        verbose: bool,
+        #[builder(setter(overridable, transform = |a: i32, b: i32| a + b))]
+        sum: i32,
+    }
+
+    let base = Foo::builder().y(1);
+    assert_eq!(
+        base.y(2).x(3).y(4).build(),
+        Foo {
+            x: 3,
+            y: Some(4),
+            items: Vec::new(),
+        }
+    );
+
+    assert_eq!(
+        Bar::builder().sum(1, 2).verbose().sum(3, 4).verbose().build(),
+        Bar { verbose: true, sum: 7 }
+    );
+}
+
+#[test]
+fn test_take_fields() {
+    #[derive(PartialEq, Debug, Clone, TypedBuilder)]
+    #[builder(into_builder)]
+    struct Foo {
+        x: String,
+        #[builder(default = 2, setter(prefix = "with_"))]
+        y: i32,
+        #[builder(default, setter(each = "item"))]
+        items: Vec<i32>,
+    }
+
+    // Middleware that rewrites a field of a builder it gets.
+    fn shout<Y, I>(builder: FooBuilder<(Set<String>, Y, I)>) -> FooBuilder<(Set<String>, Y, I)> {
+        let (x, builder) = builder.take_x();
+        builder.x(x.to_uppercase())
+    }
+
+    assert_eq!(
+        shout(Foo::builder().x("hello".to_owned()).with_y(3)).build(),
+        Foo {
+            x: "HELLO".to_owned(),
+            y: 3,
+            items: Vec::new(),
+        }
+    );
+
+    let builder = Foo::builder().item(1).with_y(3).x("a".to_owned());
+    assert_eq!(
+        builder.unset_y().unset_items().item(2).build(),
+        Foo {
+            x: "a".to_owned(),
+            y: 2,
+            items: vec![2],
+        }
+    );
+
+    // Fields of a builder that was created from an instance can be taken as well.
+    let foo = Foo::builder().x("b".to_owned()).build();
+    let (y, builder) = foo.into_builder().take_y();
+    assert_eq!(y, 2);
+    assert_eq!(builder.with_y(4).build().y, 4);
+}
+
+#[test]
+fn test_getters() {
+    #[derive(PartialEq, Debug, Clone, TypedBuilder)]
+    #[builder(into_builder, field_defaults(setter(getter)))]
+    struct Foo {
+        x: i32,
+        #[builder(default, setter(strip_option, getter(name = y_value)))]
+        y: Option<i32>,
+        #[builder(default, setter(skip))]
+        z: i32,
+        #[builder(via_mutators(init = vec![1]), mutators(
+            fn push(&mut self, item: i32) {
+                self.items.push(item);
+            }
+        ))]
+        items: Vec<i32>,
+    }
+
+    let builder = Foo::builder().x(1);
+    assert_eq!(*builder.get_x(), 1);
+    let y = builder.get_x() + 1;
+    let builder = builder.y(y);
+    assert_eq!(*builder.y_value(), Some(2));
+    assert_eq!(builder.get_items(), &[1]);
+    assert_eq!(builder.push(2).get_items(), &[1, 2]);
+
+    let foo = Foo::builder().x(3).build();
+    assert_eq!(*foo.to_builder().get_x(), 3);
+    assert_eq!(*foo.into_builder().y_value(), None);
+}
+
+#[test]
+fn test_via_mutators_getters() {
+    #[derive(TypedBuilder)]
+    struct Foo {
+        x: i32,
+        #[builder(via_mutators(init = vec![1]), mutators(
+            fn push(&mut self, item: i32) {
+                self.items.push(item);
+            }
+        ))]
+        items: Vec<i32>,
+    }
+
+    let builder = Foo::builder().push(2);
+    assert_eq!(builder.get_items(), &[1, 2]);
+    let foo = builder.x(3).build();
+    assert_eq!((foo.x, foo.items), (3, vec![1, 2]));
+}
+
+#[test]
+fn test_state_traits() {
+    #[derive(PartialEq, Debug, Clone, TypedBuilder)]
+    #[builder(into_builder)]
+    struct Foo<T> {
+        #[builder(setter(getter))]
+        x: T,
+        #[builder(default, setter(overridable, getter))]
+        y: i32,
+    }
+
+    // Works in any state in which `x` can be set - including after `into_builder()`.
+    impl<T: Default, S: foo_builder::XCanSet> FooBuilder<T, S> {
+        fn x_default(self) -> FooBuilder<T, S::WithValue<T>> {
+            self.x(T::default())
+        }
+    }
+
+    impl<S: foo_builder::XIsSet<Value = String>> FooBuilder<String, S> {
+        fn x_len(&self) -> usize {
+            self.get_x().len()
+        }
+    }
+
+    // An overridable field can be set again when it is set.
+    fn double_y<T, S>(builder: FooBuilder<T, S>) -> FooBuilder<T, S::WithValue<i32>>
+    where
+        S: foo_builder::YIsSet<Value = i32> + foo_builder::YCanSet,
+    {
+        let y = *builder.get_y();
+        builder.y(y * 2)
+    }
+
+    fn y_is_unset<T, S: foo_builder::YIsUnset>(_: &FooBuilder<T, S>) {}
+
+    let builder: FooBuilder<String, (Set<String>, Unset)> = Foo::builder().x_default();
+    y_is_unset(&builder);
+    assert_eq!(builder.x_len(), 0);
+    assert_eq!(double_y(builder.y(2)).build(), Foo { x: String::new(), y: 4 });
+
+    let foo = Foo::builder().x("foo".to_owned()).y(2).build();
+    assert_eq!(foo.to_builder().x_len(), 3);
+    assert_eq!(
+        double_y(foo.into_builder().x_default()).build(),
+        Foo { x: String::new(), y: 4 }
+    );
+}
+
+#[test]
+fn test_builder_module_name() {
+    // Already taken, so the module of the builder needs another name.
+    #[allow(dead_code)]
+    mod foo_builder {}
+
+    #[derive(PartialEq, Debug, TypedBuilder)]
+    #[builder(builder_type(module = foo_states))]
+    struct Foo {
+        x: i32,
+    }
+
+    impl<S: foo_states::XCanSet> FooBuilder<S> {
+        fn x_from_str(self, x: &str) -> FooBuilder<S::WithValue<i32>> {
+            self.x(x.parse().unwrap())
+        }
+    }
+
+    assert_eq!(Foo::builder().x_from_str("1").build(), Foo { x: 1 });
+}
+
+#[test]
+fn test_stable_builder_type_with_added_field() {
+    // The same API, before and after a field with a default is added to the struct.
+    macro_rules! api {
+        ($($field:tt)*) => {
+            use typed_builder::TypedBuilder;
+
+            #[derive(TypedBuilder)]
+            #[builder(builder_type(stable))]
+            pub struct Foo {
+                pub x: i32,
+                #[builder(default)]
+                pub y: i32,
+                $($field)*
+            }
+
+            pub fn complete(x: i32) -> FooBuilder<foo_builder::Complete> {
+                Foo::builder().x(x)
+            }
+
+            // The states in between are named from the stable ones, with the traits of the fields.
+            pub fn with_y(x: i32, y: i32) -> FooBuilder<<foo_builder::Complete as foo_builder::YState>::WithValue<i32>> {
+                complete(x).y(y)
+            }
+        };
+    }
+
+    mod v1 {
+        api!();
+    }
+
+    mod v2 {
+        api! {
+            #[builder(default = 3)]
+            pub z: i32,
+        }
+    }
+
+    let foo = v1::with_y(1, 2).build();
+    assert_eq!((foo.x, foo.y), (1, 2));
+    let foo = v2::with_y(1, 2).build();
+    assert_eq!((foo.x, foo.y, foo.z), (1, 2, 3));
+    assert_eq!(v2::complete(1).z(4).build().z, 4);
+}
+
+#[test]
+fn test_stable_builder_type() {
+    mod api {
+        use typed_builder::TypedBuilder;
+
+        #[derive(PartialEq, Debug, TypedBuilder)]
+        #[builder(builder_type(stable))]
+        pub struct Foo {
+            x: i32,
+            #[builder(default)]
+            y: i32,
+        }
+
+        #[derive(PartialEq, Debug, TypedBuilder)]
+        #[builder(builder_type(stable))]
+        pub struct Bar<'a, T: Clone + Default = i32> {
+            x: &'a T,
+            #[builder(default)]
+            y: T,
+        }
+
+        // These signatures don't change when fields with defaults are added.
+        pub fn empty() -> FooBuilder<foo_builder::Empty> {
+            Foo::builder()
+        }
+
+        pub fn complete(x: i32) -> FooBuilder<foo_builder::Complete> {
+            Foo::builder().x(x)
+        }
+
+        pub fn complete_bar<T: Clone + Default>(x: &T) -> BarBuilder<'_, T, bar_builder::Complete<'_, T>> {
+            Bar::builder().x(x)
+        }
+
+        pub fn foo(x: i32, y: i32) -> Foo {
+            Foo { x, y }
+        }
+
+        pub fn bar<T: Clone + Default>(x: &T, y: T) -> Bar<'_, T> {
+            Bar { x, y }
+        }
+
+        #[derive(PartialEq, Debug)]
+        pub struct Helper(pub i32);
+
+        pub mod baz {
+            use typed_builder::TypedBuilder;
+
+            // The aliases name the types of the fields in the same scope as the struct does.
+            #[derive(PartialEq, Debug, TypedBuilder)]
+            #[builder(builder_type(stable))]
+            pub struct Baz {
+                pub x: super::Helper,
+            }
+
+            pub fn complete(x: i32) -> BazBuilder<baz_builder::Complete> {
+                Baz::builder().x(super::Helper(x))
+            }
+        }
+    }
+
+    // The struct can also be declared in a function body.
+    #[derive(PartialEq, Debug, TypedBuilder)]
+    #[builder(builder_type(stable))]
+    struct Qux {
+        x: i32,
+    }
+
+    fn qux_builder() -> QuxBuilder<qux_builder::Empty> {
+        Qux::builder()
+    }
+
+    assert_eq!(api::empty().x(1).build(), api::foo(1, 0));
+    assert_eq!(api::complete(1).build(), api::foo(1, 0));
+    assert_eq!(api::complete(1).y(2).build(), api::foo(1, 2));
+    assert_eq!(api::complete_bar(&1).y(2).build(), api::bar(&1, 2));
+    assert_eq!(api::baz::complete(1).build(), api::baz::Baz { x: api::Helper(1) });
+    assert_eq!(qux_builder().x(1).build(), Qux { x: 1 });
+}
+
+#[test]
+fn test_build_trait() {
+    use typed_builder::{Build, HasBuilder};
+
+    mod reexported {
+        pub use typed_builder::*;
+    }
+
+    #[derive(PartialEq, Debug, TypedBuilder)]
+    struct Foo {
+        x: i32,
+        #[builder(default)]
+        y: i32,
+    }
+
+    #[derive(PartialEq, Debug, TypedBuilder)]
+    #[builder(build_method(into = Option<i32>))]
+    struct Bar {
+        x: i32,
+    }
+
+    impl From<Bar> for Option<i32> {
+        fn from(bar: Bar) -> Self {
+            Some(bar.x)
+        }
+    }
+
+    #[derive(PartialEq, Debug, TypedBuilder)]
+    #[builder(build_method(into))]
+    struct Baz {
+        x: i32,
+    }
+
+    #[derive(PartialEq, Debug, TypedBuilder)]
+    #[builder(build_method(error = &'static str, validate = |qux: &Qux| if qux.x < 0 { Err("negative") } else { Ok(()) }))]
+    struct Qux {
+        x: i32,
+    }
+
+    #[derive(PartialEq, Debug, TypedBuilder)]
+    #[builder(crate_module_path = reexported)]
+    struct Quux {
+        #[builder(default = 1)]
+        x: i32,
+    }
+
+    fn build<B: Build>(builder: B) -> B::Output {
+        builder.build()
+    }
+
+    fn build_default<T>() -> T
+    where
+        T: HasBuilder,
+        T::Builder: Build<Output = T>,
+    {
+        T::builder().build()
+    }
+
+    assert_eq!(build(Foo::builder().x(1)), Foo { x: 1, y: 0 });
+    assert_eq!(build(Foo::builder().y(2).x(1)), Foo { x: 1, y: 2 });
+    assert_eq!(build(Bar::builder().x(1)), Some(1));
+    assert_eq!(build(Baz::builder().x(1)), Baz { x: 1 });
+    assert_eq!(build(Qux::builder().x(-1)), Err("negative"));
+    assert_eq!(build(Quux::builder()), Quux { x: 1 });
+    assert_eq!(build_default::<Quux>(), Quux { x: 1 });
+}
+
+#[test]
+fn test_ordered() {
+    #[derive(PartialEq, Debug, Clone, TypedBuilder)]
+    #[builder(ordered, into_builder)]
+    struct Foo<T> {
+        x: i32,
+        #[builder(default, setter(strip_option))]
+        y: Option<i32>,
+        z: T,
+        #[builder(default, setter(each = "item"))]
+        items: Vec<i32>,
+    }
+
+    assert_eq!(
+        Foo::builder().x(1).z("a").build(),
+        Foo {
+            x: 1,
+            y: None,
+            z: "a",
+            items: vec![],
+        }
+    );
+    assert_eq!(
+        Foo::builder().x(1).z("a").item(1).y(2).build(),
+        Foo {
+            x: 1,
+            y: Some(2),
+            z: "a",
+            items: vec![1],
+        }
+    );
+
+    // A required field can be set again after the ones that follow it are unset.
+    let (z, builder) = Foo::builder().x(1).z("a").take_z();
+    assert_eq!(z, "a");
+    assert_eq!(builder.unset_x().x(2).z("b").build().x, 2);
+
+    // The setters of a prefilled builder replace the values in any order.
+    let foo = Foo::builder().x(1).z("a").build();
+    assert_eq!(
+        foo.to_builder().y(2).z("b").x(3).build(),
+        Foo {
+            x: 3,
+            y: Some(2),
+            z: "b",
+            items: vec![],
+        }
+    );
+}
+
+#[test]
+fn test_ordered_with_preset_fields() {
+    #[derive(PartialEq, Debug, TypedBuilder)]
+    #[builder(ordered, presets(local(host = "localhost")))]
+    struct Server {
+        name: &'static str,
+        host: &'static str,
+        #[builder(default = 80)]
+        port: u16,
+    }
+
+    // The preset field is skipped, but the fields before it are still set first.
+    assert_eq!(
+        Server::local_builder().name("a").port(8080).build(),
+        Server {
+            name: "a",
+            host: "localhost",
+            port: 8080,
+        }
+    );
+}
+
+#[test]
+fn test_builder_method_args() {
+    #[derive(PartialEq, Debug, TypedBuilder)]
+    #[builder(builder_method(args(id, name)))]
+    struct Foo<T: core::fmt::Display> {
+        #[builder(default)]
+        port: u16,
+        name: T,
+        id: u32,
+        #[builder(via_mutators(init = format!("{id}-{name}")))]
+        key: String,
+    }
+
+    #[typed_builder::builder_fn(builder_method(name = greeting, args(greeting)))]
+    fn greet(greeting: &str, #[builder(default = "world")] name: &str) -> String {
+        format!("{greeting}, {name}!")
+    }
+
+    #[derive(PartialEq, Debug, TypedBuilder)]
+    #[builder(runtime, builder_method(args(name, nick, len)))]
+    struct Bar {
+        #[builder(setter(into))]
+        name: String,
+        #[builder(setter(strip_option))]
+        nick: Option<&'static str>,
+        #[builder(setter(transform = |s: &str| s.len()))]
+        len: usize,
+        #[builder(default)]
+        admin: bool,
+    }
+
+    assert_eq!(
+        Foo::builder(1, "a").build(),
+        Foo {
+            port: 0,
+            name: "a",
+            id: 1,
+            key: "1-a".to_owned(),
+        }
+    );
+    assert_eq!(Foo::builder(1, "a").port(80).build().port, 80);
+    assert_eq!(greeting("Hello").call(), "Hello, world!");
+    assert_eq!(greeting("Hi").name("there").call(), "Hi, there!");
+
+    let expected = Bar {
+        name: "root".to_owned(),
+        nick: Some("r"),
+        len: 3,
+        admin: true,
+    };
+    assert_eq!(Bar::builder("root", "r", "abc").admin(true).build(), expected);
+    let mut builder = Bar::runtime_builder("root", "r", "abc");
+    builder.admin(true);
+    assert_eq!(builder.build(), Ok(expected));
+}
+
+#[test]
+fn test_presets() {
+    #[derive(PartialEq, Debug, TypedBuilder)]
+    #[builder(presets(dev(host = "localhost", debug = true), prod(host = "example.com", port = 443),))]
+    struct Config {
+        host: &'static str,
+        #[builder(default = 80)]
+        port: u16,
+        #[builder(default)]
+        debug: bool,
+        #[builder(default, setter(each = "feature"))]
+        features: Vec<&'static str>,
+    }
+
+    #[derive(PartialEq, Debug, TypedBuilder)]
+    #[builder(builder_method(args(name)), presets(admin(role = "admin")))]
+    struct User {
+        name: &'static str,
+        role: &'static str,
+    }
+
+    assert_eq!(
+        Config::dev_builder().build(),
+        Config {
+            host: "localhost",
+            port: 80,
+            debug: true,
+            features: vec![],
+        }
+    );
+    assert_eq!(
+        Config::prod_builder().port(8443).feature("tls").build(),
+        Config {
+            host: "example.com",
+            port: 8443,
+            debug: false,
+            features: vec!["tls"],
+        }
+    );
+    assert_eq!(Config::builder().host("a").build().port, 80);
+    assert_eq!(
+        User::admin_builder("root").build(),
+        User {
+            name: "root",
+            role: "admin"
+        }
+    );
+    assert_eq!(User::admin_builder("root").role("user").build().role, "user");
+}
@@ -1,33 +1,61 @@
 use proc_macro2::{Ident, Span, TokenStream};
-use quote::quote_spanned;
+use quote::{format_ident, quote_spanned};
 use syn::{parse::Error, spanned::Spanned};
 
+use crate::flatten::FlattenedSetter;
 use crate::mutator::Mutator;
-use crate::util::{expr_to_lit_string, ident_to_type, path_to_single_string, strip_raw_ident_prefix, ApplyMeta, AttrArg};
+use crate::util::{
+    expr_to_lit_string, ident_to_type, path_to_single_string, strip_raw_ident_prefix, to_pascal_case, ApplyMeta, AttrArg,
+};
 
 #[derive(Debug)]
 pub struct FieldInfo<'a> {
     pub ordinal: usize,
-    pub name: &'a syn::Ident,
+    /// The name of the field inside the builder - the field's identifier for named fields, and
+    /// `_0`, `_1`, ... (or the name set with `setter(name = ...)`) for tuple struct fields.
+    pub name: syn::Ident,
+    /// How the field is accessed when constructing the struct.
+    pub member: syn::Member,
     pub generic_ident: syn::Ident,
+    pub vis: &'a syn::Visibility,
     pub ty: &'a syn::Type,
     pub builder_attr: FieldBuilderAttr<'a>,
+    /// For flattened fields - the setters of the builder of the field's type.
+    pub flattened_setters: Vec<FlattenedSetter>,
 }
 
 impl<'a> FieldInfo<'a> {
     pub fn new(ordinal: usize, field: &'a syn::Field, field_defaults: FieldBuilderAttr<'a>) -> Result<FieldInfo<'a>, Error> {
-        if let Some(ref name) = field.ident {
-            FieldInfo {
-                ordinal,
-                name,
-                generic_ident: syn::Ident::new(&format!("__{}", strip_raw_ident_prefix(name.to_string())), Span::call_site()),
-                ty: &field.ty,
-                builder_attr: field_defaults.with(name, &field.attrs)?,
-            }
-            .post_process()
+        let mut builder_attr = field_defaults.with(&field.attrs)?;
+        if builder_attr.flatten.is_some() {
+            // The setters of a flattened field come from its type, so the defaults of the other
+            // fields don't apply to it.
+            builder_attr = FieldBuilderAttr::default().with(&field.attrs)?;
+        }
+        let (name, member) = if let Some(ref name) = field.ident {
+            (name.clone(), syn::Member::Named(name.clone()))
         } else {
-            Err(Error::new(field.span(), "Nameless field in struct"))
+            let name = builder_attr
+                .setter
+                .name
+                .clone()
+                .unwrap_or_else(|| format_ident!("_{}", ordinal, span = field.ty.span()));
+            (name, syn::Member::Unnamed(ordinal.into()))
+        };
+        for mutator in builder_attr.mutators.iter_mut() {
+            mutator.required_fields.insert(name.clone());
+        }
+        FieldInfo {
+            ordinal,
+            generic_ident: syn::Ident::new(&format!("__{}", strip_raw_ident_prefix(name.to_string())), Span::call_site()),
+            name,
+            member,
+            vis: &field.vis,
+            ty: &field.ty,
+            builder_attr,
+            flattened_setters: Vec::new(),
         }
+        .post_process()
     }
 
     pub fn generic_ty_param(&self) -> syn::GenericParam {
@@ -38,17 +66,6 @@ impl<'a> FieldInfo<'a> {
         ident_to_type(self.generic_ident.clone())
     }
 
-    pub fn tuplized_type_ty_param(&self) -> syn::Type {
-        let mut types = syn::punctuated::Punctuated::default();
-        types.push(self.ty.clone());
-        types.push_punct(Default::default());
-        syn::TypeTuple {
-            paren_token: Default::default(),
-            elems: types,
-        }
-        .into()
-    }
-
     pub fn type_from_inside_option(&self) -> Option<&syn::Type> {
         let path = if let syn::Type::Path(type_path) = self.ty {
             if type_path.qself.is_some() {
@@ -74,8 +91,27 @@ impl<'a> FieldInfo<'a> {
         }
     }
 
+    /// The name the build method gives the state of the field until it is resolved - which is not
+    /// the name of the field, so that the field only shadows other items with its name once it's
+    /// resolved.
+    pub fn state_binding_name(&self) -> Ident {
+        format_ident!("__typed_builder_{}", strip_raw_ident_prefix(self.name.to_string()))
+    }
+
+    /// The name of the getter of the field, which defaults to the name of the field with `get_`
+    /// before it.
+    pub fn getter_method_name(&self) -> Ident {
+        self.builder_attr
+            .setter
+            .getter
+            .as_ref()
+            .and_then(|getter| getter.name.clone())
+            .unwrap_or_else(|| format_ident!("get_{}", strip_raw_ident_prefix(self.name.to_string())))
+    }
+
     pub fn setter_method_name(&self) -> Ident {
-        let name = strip_raw_ident_prefix(self.name.to_string());
+        let base_name = self.builder_attr.setter.name.as_ref().unwrap_or(&self.name);
+        let name = strip_raw_ident_prefix(base_name.to_string());
 
         if let (Some(prefix), Some(suffix)) = (&self.builder_attr.setter.prefix, &self.builder_attr.setter.suffix) {
             Ident::new(&format!("{}{}{}", prefix, name, suffix), Span::call_site())
@@ -84,11 +120,58 @@ impl<'a> FieldInfo<'a> {
         } else if let Some(suffix) = &self.builder_attr.setter.suffix {
             Ident::new(&format!("{}{}", name, suffix), Span::call_site())
         } else {
-            self.name.clone()
+            base_name.clone()
+        }
+    }
+
+    /// The trait in the builder's module that is implemented for all the states of the builder, for
+    /// changing the state of the field.
+    pub fn state_trait_name(&self) -> Ident {
+        self.field_trait_name("State")
+    }
+
+    /// The trait in the builder's module that is implemented for the states of the builder in which
+    /// the field can be set.
+    pub fn can_set_trait_name(&self) -> Ident {
+        self.field_trait_name("CanSet")
+    }
+
+    /// The trait in the builder's module that is implemented for the states of the builder in which
+    /// the field is not set.
+    pub fn is_unset_trait_name(&self) -> Ident {
+        self.field_trait_name("IsUnset")
+    }
+
+    /// The trait in the builder's module that is implemented for the states of the builder in which
+    /// the field is set.
+    pub fn is_set_trait_name(&self) -> Ident {
+        self.field_trait_name("IsSet")
+    }
+
+    fn field_trait_name(&self, suffix: &str) -> Ident {
+        let name = to_pascal_case(&strip_raw_ident_prefix(self.name.to_string()));
+        if name.starts_with(|c: char| c.is_ascii_digit()) {
+            format_ident!("_{}{}", name, suffix, span = self.name.span())
+        } else {
+            format_ident!("{}{}", name, suffix, span = self.name.span())
         }
     }
 
     fn post_process(mut self) -> Result<Self, Error> {
+        if let Some(each) = &self.builder_attr.setter.each {
+            if *each == self.setter_method_name() {
+                return Err(Error::new_spanned(
+                    each,
+                    "the setter for a single item must be named differently than the setter of the field",
+                ));
+            }
+            // The collection starts out empty, and stays empty if no item is added.
+            if self.builder_attr.default.is_none() {
+                self.builder_attr.default =
+                    Some(syn::parse2(quote_spanned!(each.span() => ::core::default::Default::default())).unwrap());
+            }
+        }
+
         if let Some(ref strip_bool_span) = self.builder_attr.setter.strip_bool {
             if let Some(default_span) = self.builder_attr.default.as_ref().map(Spanned::span) {
                 let mut error = Error::new(
@@ -119,6 +202,11 @@ pub struct FieldBuilderAttr<'a> {
     /// Functions that are able to mutate fields in the builder that are already set
     pub mutators: Vec<Mutator>,
     pub mutable_during_default_resolution: Option<Span>,
+    /// Function that checks the resolved value of the field when the builder is finalised
+    pub validate: Option<syn::Expr>,
+    /// Whether the setters of the field's type's builder are exposed on the builder instead of a
+    /// setter for the field.
+    pub flatten: Option<Span>,
 }
 
 #[derive(Debug, Default, Clone)]
@@ -131,10 +219,19 @@ pub struct SetterSettings {
     pub transform: Option<Transform>,
     pub prefix: Option<String>,
     pub suffix: Option<String>,
+    pub name: Option<syn::Ident>,
+    /// The name of a setter that adds a single item to the collection in the field.
+    pub each: Option<syn::Ident>,
+    /// Whether the setter takes a closure that fills in the builder of the field's type.
+    pub nested: Option<Span>,
+    /// Whether the setter can be called again after the field is set, replacing its value.
+    pub overridable: Option<Span>,
+    /// The getter the builder has once the field is set.
+    pub getter: Option<GetterSettings>,
 }
 
 impl<'a> FieldBuilderAttr<'a> {
-    pub fn with(mut self, name: &Ident, attrs: &'a [syn::Attribute]) -> Result<Self, Error> {
+    pub fn with(mut self, attrs: &'a [syn::Attribute]) -> Result<Self, Error> {
         for attr in attrs {
             let list = match &attr.meta {
                 syn::Meta::List(list) => {
@@ -165,16 +262,92 @@ impl<'a> FieldBuilderAttr<'a> {
             self.apply_subsections(list)?;
         }
 
-        for mutator in self.mutators.iter_mut() {
-            mutator.required_fields.insert(name.clone());
-        }
-
         self.inter_fields_conflicts()?;
 
         Ok(self)
     }
 
     fn inter_fields_conflicts(&self) -> Result<(), Error> {
+        if let Some(each) = &self.setter.each {
+            let conflicting = [
+                ("skip", self.setter.skip),
+                ("strip_option", self.setter.strip_option),
+                ("strip_bool", self.setter.strip_bool),
+                ("transform", self.setter.transform.as_ref().map(|t| t.span)),
+                ("via_mutators", self.via_mutators.as_ref().map(|v| v.span)),
+            ];
+            if let Some((caption, span)) = conflicting.iter().find_map(|(caption, span)| Some((caption, (*span)?))) {
+                let mut error = Error::new_spanned(each, format_args!("each conflicts with {}", caption));
+                error.combine(Error::new(span, format_args!("{} set here", caption)));
+                return Err(error);
+            }
+        }
+
+        if let Some(flatten) = self.flatten {
+            let conflicting = [
+                ("default", self.default.as_ref().map(Spanned::span)),
+                ("via_mutators", self.via_mutators.as_ref().map(|v| v.span)),
+                ("mutators", self.mutators.first().map(|m| m.fun.sig.ident.span())),
+                ("validate", self.validate.as_ref().map(Spanned::span)),
+                ("mutable_during_default_resolution", self.mutable_during_default_resolution),
+            ];
+            if let Some((caption, span)) = conflicting.iter().find_map(|(caption, span)| Some((caption, (*span)?))) {
+                let mut error = Error::new(flatten, format_args!("flatten conflicts with {}", caption));
+                error.combine(Error::new(span, format_args!("{} set here", caption)));
+                return Err(error);
+            }
+            let SetterSettings {
+                doc,
+                skip,
+                auto_into,
+                strip_option,
+                strip_bool,
+                transform,
+                prefix,
+                suffix,
+                name,
+                each,
+                nested,
+                overridable,
+                getter,
+            } = &self.setter;
+            if doc.is_some()
+                || skip.is_some()
+                || auto_into.is_some()
+                || strip_option.is_some()
+                || strip_bool.is_some()
+                || transform.is_some()
+                || prefix.is_some()
+                || suffix.is_some()
+                || name.is_some()
+                || each.is_some()
+                || nested.is_some()
+                || overridable.is_some()
+                || getter.is_some()
+            {
+                return Err(Error::new(
+                    flatten,
+                    "flatten conflicts with setter settings - the setters of a flattened field come from the builder of its type",
+                ));
+            }
+        }
+
+        if let Some(nested) = self.setter.nested {
+            let conflicting = [
+                ("skip", self.setter.skip),
+                ("into", self.setter.auto_into),
+                ("strip_bool", self.setter.strip_bool),
+                ("transform", self.setter.transform.as_ref().map(|t| t.span)),
+                ("each", self.setter.each.as_ref().map(|each| each.span())),
+                ("via_mutators", self.via_mutators.as_ref().map(|v| v.span)),
+            ];
+            if let Some((caption, span)) = conflicting.iter().find_map(|(caption, span)| Some((caption, (*span)?))) {
+                let mut error = Error::new(nested, format_args!("nested conflicts with {}", caption));
+                error.combine(Error::new(span, format_args!("{} set here", caption)));
+                return Err(error);
+            }
+        }
+
         if let (Some(skip), None) = (&self.setter.skip, &self.default) {
             return Err(Error::new(
                 *skip,
@@ -240,6 +413,16 @@ impl ApplyMeta for FieldBuilderAttr<'_> {
                 Ok(())
             }
             "setter" => self.setter.apply_sub_attr(expr.sub_attr()?),
+            "validate" => {
+                if let AttrArg::Flag(name) = expr {
+                    return Err(Error::new_spanned(
+                        name,
+                        "`validate` needs the function that checks the field's value: `validate = ...`",
+                    ));
+                }
+                self.validate = expr.key_value_or_not()?.map(|kv| kv.parse_value()).transpose()?;
+                Ok(())
+            }
             "mutable_during_default_resolution" => expr.apply_flag_to_field(
                 &mut self.mutable_during_default_resolution,
                 "made mutable during default resolution",
@@ -283,6 +466,7 @@ impl ApplyMeta for FieldBuilderAttr<'_> {
                 self.mutators.extend(expr.sub_attr()?.undelimited()?);
                 Ok(())
             }
+            "flatten" => expr.apply_flag_to_field(&mut self.flatten, "flattened"),
             _ => Err(Error::new_spanned(
                 expr.name(),
                 format!("Unknown parameter {:?}", expr.name().to_string()),
@@ -322,10 +506,39 @@ impl ApplyMeta for SetterSettings {
                 };
                 Ok(())
             }
+            "name" => {
+                self.name = expr.key_value_or_not()?.map(|kv| kv.parse_value()).transpose()?;
+                Ok(())
+            }
+            "each" => {
+                self.each = if let Some(key_value) = expr.key_value_or_not()? {
+                    Some(key_value.parse_value::<syn::LitStr>()?.parse()?)
+                } else {
+                    None
+                };
+                Ok(())
+            }
             "skip" => expr.apply_flag_to_field(&mut self.skip, "skipped"),
             "into" => expr.apply_flag_to_field(&mut self.auto_into, "calling into() on the argument"),
             "strip_option" => expr.apply_flag_to_field(&mut self.strip_option, "putting the argument in Some(...)"),
             "strip_bool" => expr.apply_flag_to_field(&mut self.strip_bool, "zero arguments setter, sets the field to true"),
+            "nested" => expr.apply_flag_to_field(&mut self.nested, "built with the builder of its type"),
+            "overridable" => expr.apply_flag_to_field(&mut self.overridable, "overridable"),
+            "getter" => {
+                match expr {
+                    AttrArg::Flag(_) => {
+                        self.getter.get_or_insert_with(GetterSettings::default);
+                    }
+                    AttrArg::Not { .. } => {
+                        self.getter = None;
+                    }
+                    AttrArg::Sub(sub) => {
+                        self.getter.get_or_insert_with(GetterSettings::default).apply_sub_attr(sub)?;
+                    }
+                    AttrArg::KeyValue(_) => return Err(expr.incorrect_type()),
+                }
+                Ok(())
+            }
             _ => Err(Error::new_spanned(
                 expr.name(),
                 format!("Unknown parameter {:?}", expr.name().to_string()),
@@ -369,6 +582,32 @@ fn parse_transform_closure(span: Span, expr: syn::Expr) -> Result<Transform, Err
     })
 }
 
+/// Settings of the getter of a field.
+#[derive(Debug, Default, Clone)]
+pub struct GetterSettings {
+    pub name: Option<syn::Ident>,
+    pub doc: Option<syn::Expr>,
+}
+
+impl ApplyMeta for GetterSettings {
+    fn apply_meta(&mut self, expr: AttrArg) -> Result<(), Error> {
+        match expr.name().to_string().as_str() {
+            "name" => {
+                self.name = expr.key_value_or_not()?.map(|kv| kv.parse_value()).transpose()?;
+                Ok(())
+            }
+            "doc" => {
+                self.doc = expr.key_value_or_not()?.map(|kv| kv.parse_value()).transpose()?;
+                Ok(())
+            }
+            _ => Err(Error::new_spanned(
+                expr.name(),
+                format!("Unknown parameter {:?}", expr.name().to_string()),
+            )),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ViaMutators {
     pub span: Span,
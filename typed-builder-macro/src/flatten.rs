@@ -0,0 +1,128 @@
+use proc_macro2::{Ident, TokenStream};
+use quote::{quote, ToTokens};
+use syn::parse::{Parse, ParseStream, Parser};
+use syn::{Error, Token};
+
+use crate::struct_info::StructInfo;
+
+/// A setter that a builder exposes to the builders of the structs that flatten its struct.
+///
+/// The setters are passed between the derives as `name field arity;` - where `arity` is `_` for
+/// setters that cannot be flattened - and their position in the list is the index of their
+/// `FlattenSetter` implementation.
+#[derive(Debug, Clone)]
+pub struct FlattenedSetter {
+    pub name: Ident,
+    /// The field the setter sets, for reporting name clashes.
+    pub field: Ident,
+    /// The number of arguments of the setter, if it can be flattened.
+    pub arity: Option<usize>,
+}
+
+impl Parse for FlattenedSetter {
+    fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
+        let name = input.parse()?;
+        let field = input.parse()?;
+        let arity = if input.peek(Token![_]) {
+            input.parse::<Token![_]>()?;
+            None
+        } else {
+            Some(input.parse::<syn::LitInt>()?.base10_parse()?)
+        };
+        input.parse::<Token![;]>()?;
+        Ok(Self { name, field, arity })
+    }
+}
+
+impl ToTokens for FlattenedSetter {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let Self { name, field, arity } = self;
+        let arity = match arity {
+            Some(arity) => proc_macro2::Literal::usize_unsuffixed(*arity).into_token_stream(),
+            None => quote!(_),
+        };
+        quote!(#name #field #arity;).to_tokens(tokens);
+    }
+}
+
+/// The input of `__flatten_builder`: the struct that flattens other structs, followed by the
+/// setters of each of the flattened fields that were resolved so far.
+struct FlattenInput {
+    ast: syn::DeriveInput,
+    resolved: Vec<Vec<FlattenedSetter>>,
+}
+
+impl Parse for FlattenInput {
+    fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
+        let content;
+        syn::braced!(content in input);
+        let ast = content.parse()?;
+        let mut resolved = Vec::new();
+        while !input.is_empty() {
+            let content;
+            syn::braced!(content in input);
+            let mut setters = Vec::new();
+            while !content.is_empty() {
+                setters.push(content.parse()?);
+            }
+            resolved.push(setters);
+        }
+        Ok(Self { ast, resolved })
+    }
+}
+
+pub fn impl_flatten_builder(input: TokenStream) -> syn::Result<TokenStream> {
+    let FlattenInput { ast, resolved } = FlattenInput::parse.parse2(input)?;
+    let syn::Data::Struct(syn::DataStruct {
+        fields:
+            syn::Fields::Named(syn::FieldsNamed { named: fields, .. })
+            | syn::Fields::Unnamed(syn::FieldsUnnamed { unnamed: fields, .. }),
+        ..
+    }) = &ast.data
+    else {
+        return Err(Error::new_spanned(&ast.ident, "flatten is only supported in structs"));
+    };
+    let mut struct_info = StructInfo::new(&ast, fields.iter())?;
+    resolve_flattened_fields(&ast, &mut struct_info, resolved)
+}
+
+/// Generate the builder if the setters of all the flattened fields are resolved, or ask the type
+/// of the next flattened field for its setters.
+///
+/// The derive of every `flattenable` struct defines a macro with the same name as the struct,
+/// which calls `__flatten_builder` with the setters of its builder added to its input.
+pub fn resolve_flattened_fields(
+    ast: &syn::DeriveInput,
+    struct_info: &mut StructInfo<'_>,
+    resolved: Vec<Vec<FlattenedSetter>>,
+) -> syn::Result<TokenStream> {
+    let flattened_count = struct_info.flattened_fields().count();
+    if let Some(next) = struct_info.flattened_fields().nth(resolved.len()) {
+        let mut macro_path = match next.ty {
+            syn::Type::Path(syn::TypePath { qself: None, path }) => path.clone(),
+            ty => return Err(Error::new_spanned(ty, "only structs can be flattened")),
+        };
+        if let Some(last) = macro_path.segments.last_mut() {
+            last.arguments = syn::PathArguments::None;
+        }
+        let resolved = resolved.iter().map(|setters| quote!({ #( #setters )* }));
+        return Ok(quote! {
+            #macro_path! { { #ast } #( #resolved )* }
+        });
+    }
+    if resolved.len() != flattened_count {
+        return Err(Error::new_spanned(
+            &ast.ident,
+            "setters were resolved for too many flattened fields",
+        ));
+    }
+
+    let mut resolved = resolved.into_iter();
+    for field in struct_info.fields.iter_mut() {
+        if field.builder_attr.flatten.is_some() {
+            field.flattened_setters = resolved.next().unwrap_or_default();
+        }
+    }
+    struct_info.check_flattened_setters()?;
+    crate::impl_builder(struct_info)
+}
@@ -0,0 +1,477 @@
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::spanned::Spanned;
+use syn::visit::Visit;
+use syn::visit_mut::VisitMut;
+use syn::{parse_quote, parse_quote_spanned, Error, FnArg, Pat};
+
+use crate::struct_info::{BuilderTarget, StructInfo, TypeBuilderAttr};
+use crate::util::{path_to_single_string, strip_raw_ident_prefix};
+
+/// A function whose arguments are turned into the fields of a builder.
+pub struct FnInfo {
+    /// The name of the function.
+    pub name: syn::Ident,
+    /// The original function, without its documentation and without the `#[builder(...)]`
+    /// attributes on its arguments. Methods are renamed, so that the function that creates the
+    /// builder can take their place.
+    pub item: syn::ItemFn,
+    pub doc: Vec<syn::Attribute>,
+    /// Fields of the builder, one for each argument of the function (including the receiver of a
+    /// method).
+    pub fields: Vec<syn::Field>,
+    /// The generics of the function (and of its `impl` block), with a lifetime parameter added for
+    /// each lifetime that was elided in the types of the arguments.
+    pub generics: syn::Generics,
+    pub output: syn::Type,
+    pub method: Option<MethodInfo>,
+}
+
+/// The context of a function defined inside an `impl` block.
+#[derive(Debug)]
+pub struct MethodInfo {
+    /// The generics of the `impl` block.
+    pub impl_generics: syn::Generics,
+    pub self_ty: syn::Type,
+    /// The name of the type the `impl` block is for.
+    pub self_name: syn::Ident,
+    /// The generics of the method, with the lifetime parameters added for the elided lifetimes.
+    pub method_generics: syn::Generics,
+    /// The type of the receiver, if the method has one.
+    pub receiver: Option<syn::Type>,
+    /// Whether the method is an associated function that returns `Self`.
+    pub constructor: bool,
+}
+
+impl FnInfo {
+    pub fn new(item: syn::ItemFn) -> syn::Result<Self> {
+        Self::new_impl(item, None)
+    }
+
+    pub fn new_method(item: syn::ItemFn, item_impl: &syn::ItemImpl) -> syn::Result<Self> {
+        Self::new_impl(item, Some(item_impl))
+    }
+
+    fn new_impl(mut item: syn::ItemFn, item_impl: Option<&syn::ItemImpl>) -> syn::Result<Self> {
+        let (doc, attrs) = item
+            .attrs
+            .drain(..)
+            .partition(|attr| path_to_single_string(attr.path()).as_deref() == Some("doc"));
+        item.attrs = attrs;
+
+        let name = item.sig.ident.clone();
+        let replace_self = |mut ty: syn::Type| {
+            if let Some(item_impl) = item_impl {
+                ReplaceSelf(&item_impl.self_ty).visit_type_mut(&mut ty);
+            }
+            ty
+        };
+
+        let mut method_generics = item.sig.generics.clone();
+        let mut elided_lifetimes = ElidedLifetimes {
+            generics: &mut method_generics,
+            count: 0,
+        };
+        let mut receiver = None;
+        let fields = item
+            .sig
+            .inputs
+            .iter_mut()
+            .map(|input| {
+                let input = match input {
+                    FnArg::Typed(input) => input,
+                    FnArg::Receiver(syn::Receiver { attrs, self_token, ty, .. }) => {
+                        if item_impl.is_none() {
+                            return Err(Error::new_spanned(
+                                self_token,
+                                "methods with `builder_fn` must be inside an `impl` block marked with `#[typed_builder::builder_impl]`",
+                            ));
+                        }
+                        if let Some(attr) = attrs.iter().find(|attr| attr.path().is_ident("builder")) {
+                            return Err(Error::new_spanned(attr, "the receiver of a method cannot have builder settings"));
+                        }
+                        let mut ty = (**ty).clone();
+                        elided_lifetimes.visit_type_mut(&mut ty);
+                        receiver = Some(ty.clone());
+                        return Ok(syn::Field {
+                            attrs: vec![parse_quote_spanned!(self_token.span => #[builder(via_mutators(init = self))])],
+                            vis: syn::Visibility::Inherited,
+                            mutability: syn::FieldMutability::None,
+                            ident: Some(Ident::new("__receiver", self_token.span)),
+                            colon_token: None,
+                            ty: replace_self(ty),
+                        });
+                    }
+                };
+                let ident = match &*input.pat {
+                    Pat::Ident(syn::PatIdent {
+                        by_ref: None,
+                        subpat: None,
+                        ident,
+                        ..
+                    }) => ident.clone(),
+                    pat => {
+                        return Err(Error::new_spanned(
+                            pat,
+                            "`builder_fn` only supports arguments that are bound to a plain identifier",
+                        ))
+                    }
+                };
+                if let Some(impl_trait) = find_impl_trait(&input.ty) {
+                    return Err(Error::new_spanned(
+                        impl_trait,
+                        "`impl Trait` arguments are not supported by `builder_fn` - use a generic parameter instead",
+                    ));
+                }
+                let (builder_attrs, attrs) = input
+                    .attrs
+                    .drain(..)
+                    .partition(|attr| path_to_single_string(attr.path()).as_deref() == Some("builder"));
+                input.attrs = attrs;
+                let mut ty = (*input.ty).clone();
+                elided_lifetimes.visit_type_mut(&mut ty);
+                Ok(syn::Field {
+                    attrs: builder_attrs,
+                    vis: syn::Visibility::Inherited,
+                    mutability: syn::FieldMutability::None,
+                    ident: Some(ident),
+                    colon_token: Some(input.colon_token),
+                    ty: replace_self(ty),
+                })
+            })
+            .collect::<syn::Result<Vec<_>>>()?;
+
+        let mut output = match &item.sig.output {
+            syn::ReturnType::Default => syn::parse_quote!(()),
+            syn::ReturnType::Type(_, ty) => (**ty).clone(),
+        };
+        let constructor =
+            receiver.is_none() && matches!(&output, syn::Type::Path(path) if path.qself.is_none() && path.path.is_ident("Self"));
+        output = replace_self(output);
+        // Like in function signatures, lifetimes elided in the output are inferred from a receiver
+        // passed by reference, or else only when the arguments contain exactly one lifetime.
+        let mut input_lifetimes = InputLifetimes::default();
+        for field in fields.iter() {
+            input_lifetimes.visit_type(&field.ty);
+        }
+        if let Some(syn::Type::Reference(syn::TypeReference {
+            lifetime: Some(lifetime),
+            ..
+        })) = &receiver
+        {
+            ReplaceElidedLifetimes(lifetime).visit_type_mut(&mut output);
+        } else if let [lifetime] = &input_lifetimes.lifetimes[..] {
+            ReplaceElidedLifetimes(lifetime).visit_type_mut(&mut output);
+        }
+
+        let (generics, method) = if let Some(item_impl) = item_impl {
+            let self_name = match &*item_impl.self_ty {
+                syn::Type::Path(syn::TypePath { qself: None, path }) => path.segments.last().map(|segment| segment.ident.clone()),
+                _ => None,
+            }
+            .ok_or_else(|| {
+                Error::new_spanned(
+                    &item_impl.self_ty,
+                    "`builder_impl` only supports `impl` blocks of named types",
+                )
+            })?;
+            item.sig.ident = format_ident!(
+                "__typed_builder_{}",
+                strip_raw_ident_prefix(name.to_string()),
+                span = name.span()
+            );
+            let method = MethodInfo {
+                impl_generics: item_impl.generics.clone(),
+                self_ty: (*item_impl.self_ty).clone(),
+                self_name,
+                method_generics: method_generics.clone(),
+                receiver,
+                constructor,
+            };
+            ReplaceSelf(&item_impl.self_ty).visit_generics_mut(&mut method_generics);
+            (merge_generics(&item_impl.generics, method_generics), Some(method))
+        } else {
+            (method_generics, None)
+        };
+
+        Ok(Self {
+            name,
+            item,
+            doc,
+            fields,
+            generics,
+            output,
+            method,
+        })
+    }
+
+    pub fn struct_info<'a>(&'a self, builder_attr: TypeBuilderAttr<'a>) -> syn::Result<StructInfo<'a>> {
+        StructInfo::new_impl(
+            &self.item.vis,
+            &self.name,
+            BuilderTarget::Function {
+                item: &self.item,
+                doc: &self.doc,
+                output: &self.output,
+                method: self.method.as_ref(),
+            },
+            &self.generics,
+            builder_attr,
+            self.fields.iter(),
+        )
+    }
+}
+
+pub fn impl_builder_fn(args: TokenStream, item: syn::ItemFn) -> syn::Result<TokenStream> {
+    let builder_attr = TypeBuilderAttr::from_args(args)?;
+    let fn_info = FnInfo::new(item)?;
+    let struct_info = fn_info.struct_info(builder_attr)?;
+    crate::impl_builder(&struct_info)
+}
+
+pub fn impl_builder_impl(args: TokenStream, mut item_impl: syn::ItemImpl) -> syn::Result<TokenStream> {
+    if !args.is_empty() {
+        return Err(Error::new_spanned(args, "`builder_impl` does not take arguments"));
+    }
+    if let Some((_, path, _)) = &item_impl.trait_ {
+        return Err(Error::new_spanned(
+            path,
+            "`builder_impl` only supports inherent `impl` blocks",
+        ));
+    }
+
+    let mut builders = TokenStream::new();
+    let mut items = Vec::with_capacity(item_impl.items.len());
+    for impl_item in std::mem::take(&mut item_impl.items) {
+        let mut method = match impl_item {
+            syn::ImplItem::Fn(method) => method,
+            impl_item => {
+                items.push(impl_item);
+                continue;
+            }
+        };
+        let Some(position) = method.attrs.iter().position(is_builder_fn_attr) else {
+            items.push(syn::ImplItem::Fn(method));
+            continue;
+        };
+        let args = match method.attrs.remove(position).meta {
+            syn::Meta::Path(_) => TokenStream::new(),
+            syn::Meta::List(list) => list.tokens,
+            meta @ syn::Meta::NameValue(_) => return Err(Error::new_spanned(meta, "Expected builder_fn(…)")),
+        };
+        if let Some(defaultness) = method.defaultness {
+            return Err(Error::new_spanned(defaultness, "`builder_fn` does not support `default fn`"));
+        }
+
+        let fn_info = FnInfo::new_method(
+            syn::ItemFn {
+                attrs: method.attrs,
+                vis: method.vis,
+                sig: method.sig,
+                block: Box::new(method.block),
+            },
+            &item_impl,
+        )?;
+        let struct_info = fn_info.struct_info(TypeBuilderAttr::from_args(args)?)?;
+        builders.extend(crate::impl_builder(&struct_info)?);
+        drop(struct_info);
+
+        // The builder calls the original method, which keeps its place in the `impl` block under a
+        // hidden name.
+        let syn::ItemFn {
+            mut attrs, sig, block, ..
+        } = fn_info.item;
+        attrs.push(parse_quote!(#[doc(hidden)]));
+        attrs.push(parse_quote!(#[allow(dead_code)]));
+        items.push(syn::ImplItem::Fn(syn::ImplItemFn {
+            attrs,
+            vis: syn::Visibility::Inherited,
+            defaultness: None,
+            sig,
+            block: *block,
+        }));
+    }
+    item_impl.items = items;
+
+    Ok(quote! {
+        #item_impl
+        #builders
+    })
+}
+
+/// The output of a `builder_fn` or `builder_impl` that failed: the error, next to the original item
+/// without the attributes that only the builder understands - so that the code that uses the item
+/// does not get errors of its own.
+pub fn original_item_with_error(error: Error, mut item: syn::Item) -> TokenStream {
+    StripBuilderAttrs.visit_item_mut(&mut item);
+    let error = error.to_compile_error();
+    quote! {
+        #error
+        #item
+    }
+}
+
+fn is_builder_fn_attr(attr: &syn::Attribute) -> bool {
+    attr.path()
+        .segments
+        .last()
+        .is_some_and(|segment| segment.ident == "builder_fn")
+}
+
+/// Combine the generics of an `impl` block with those of a method inside it.
+fn merge_generics(impl_generics: &syn::Generics, method_generics: syn::Generics) -> syn::Generics {
+    let (lifetimes, others): (Vec<_>, Vec<_>) = impl_generics
+        .params
+        .iter()
+        .cloned()
+        .chain(method_generics.params)
+        .partition(|param| matches!(param, syn::GenericParam::Lifetime(_)));
+    let where_clause = match (impl_generics.where_clause.clone(), method_generics.where_clause) {
+        (Some(mut where_clause), Some(method_where_clause)) => {
+            where_clause.predicates.extend(method_where_clause.predicates);
+            Some(where_clause)
+        }
+        (where_clause, None) | (None, where_clause) => where_clause,
+    };
+    syn::Generics {
+        lt_token: Some(Default::default()),
+        params: lifetimes.into_iter().chain(others).collect(),
+        gt_token: Some(Default::default()),
+        where_clause,
+    }
+}
+
+fn find_impl_trait(ty: &syn::Type) -> Option<&syn::TypeImplTrait> {
+    #[derive(Default)]
+    struct FindImplTrait<'ast>(Option<&'ast syn::TypeImplTrait>);
+
+    impl<'ast> Visit<'ast> for FindImplTrait<'ast> {
+        fn visit_type_impl_trait(&mut self, impl_trait: &'ast syn::TypeImplTrait) {
+            self.0.get_or_insert(impl_trait);
+        }
+    }
+
+    let mut visitor = FindImplTrait::default();
+    visitor.visit_type(ty);
+    visitor.0
+}
+
+/// Gives names to the lifetimes elided in the types of the arguments, adding them to the generics
+/// of the builder.
+struct ElidedLifetimes<'a> {
+    generics: &'a mut syn::Generics,
+    count: usize,
+}
+
+impl ElidedLifetimes<'_> {
+    fn fresh_lifetime(&mut self, span: Span) -> syn::Lifetime {
+        let lifetime = syn::Lifetime {
+            apostrophe: span,
+            ident: format_ident!("__elided{}", self.count, span = span),
+        };
+        self.count += 1;
+        // Lifetime parameters must come before the other generic parameters.
+        let position = self.generics.lifetimes().count();
+        self.generics.params.insert(
+            position,
+            syn::GenericParam::Lifetime(syn::LifetimeParam::new(lifetime.clone())),
+        );
+        lifetime
+    }
+}
+
+impl VisitMut for ElidedLifetimes<'_> {
+    fn visit_type_reference_mut(&mut self, reference: &mut syn::TypeReference) {
+        if reference.lifetime.is_none() {
+            reference.lifetime = Some(self.fresh_lifetime(reference.and_token.span()));
+        }
+        syn::visit_mut::visit_type_reference_mut(self, reference);
+    }
+
+    fn visit_lifetime_mut(&mut self, lifetime: &mut syn::Lifetime) {
+        if lifetime.ident == "_" {
+            *lifetime = self.fresh_lifetime(lifetime.span());
+        }
+    }
+
+    // Function pointers and `Fn` traits have their own lifetime elision rules.
+    fn visit_type_bare_fn_mut(&mut self, _: &mut syn::TypeBareFn) {}
+    fn visit_parenthesized_generic_arguments_mut(&mut self, _: &mut syn::ParenthesizedGenericArguments) {}
+}
+
+#[derive(Default)]
+struct InputLifetimes {
+    lifetimes: Vec<syn::Lifetime>,
+}
+
+impl Visit<'_> for InputLifetimes {
+    fn visit_lifetime(&mut self, lifetime: &syn::Lifetime) {
+        if lifetime.ident != "static" && !self.lifetimes.contains(lifetime) {
+            self.lifetimes.push(lifetime.clone());
+        }
+    }
+
+    fn visit_type_bare_fn(&mut self, _: &syn::TypeBareFn) {}
+    fn visit_parenthesized_generic_arguments(&mut self, _: &syn::ParenthesizedGenericArguments) {}
+}
+
+struct ReplaceElidedLifetimes<'a>(&'a syn::Lifetime);
+
+impl VisitMut for ReplaceElidedLifetimes<'_> {
+    fn visit_type_reference_mut(&mut self, reference: &mut syn::TypeReference) {
+        if reference.lifetime.is_none() {
+            reference.lifetime = Some(self.0.clone());
+        }
+        syn::visit_mut::visit_type_reference_mut(self, reference);
+    }
+
+    fn visit_lifetime_mut(&mut self, lifetime: &mut syn::Lifetime) {
+        if lifetime.ident == "_" {
+            *lifetime = self.0.clone();
+        }
+    }
+
+    fn visit_type_bare_fn_mut(&mut self, _: &mut syn::TypeBareFn) {}
+    fn visit_parenthesized_generic_arguments_mut(&mut self, _: &mut syn::ParenthesizedGenericArguments) {}
+}
+
+/// Replaces `Self` with the type of the `impl` block, so that types can be used outside of it.
+struct ReplaceSelf<'a>(&'a syn::Type);
+
+impl VisitMut for ReplaceSelf<'_> {
+    fn visit_type_mut(&mut self, ty: &mut syn::Type) {
+        if let syn::Type::Path(syn::TypePath { qself: None, path }) = ty {
+            if path.segments.first().is_some_and(|segment| segment.ident == "Self") {
+                if path.segments.len() == 1 {
+                    *ty = self.0.clone();
+                } else {
+                    let self_ty = self.0;
+                    let rest = path.segments.iter().skip(1);
+                    *ty = parse_quote!(<#self_ty>#(::#rest)*);
+                }
+                return;
+            }
+        }
+        syn::visit_mut::visit_type_mut(self, ty);
+    }
+}
+
+struct StripBuilderAttrs;
+
+impl VisitMut for StripBuilderAttrs {
+    fn visit_fn_arg_mut(&mut self, arg: &mut FnArg) {
+        let attrs = match arg {
+            FnArg::Receiver(receiver) => &mut receiver.attrs,
+            FnArg::Typed(arg) => &mut arg.attrs,
+        };
+        attrs.retain(|attr| path_to_single_string(attr.path()).as_deref() != Some("builder"));
+    }
+
+    fn visit_impl_item_fn_mut(&mut self, method: &mut syn::ImplItemFn) {
+        method.attrs.retain(|attr| !is_builder_fn_attr(attr));
+        syn::visit_mut::visit_impl_item_fn_mut(self, method);
+    }
+
+    // Only the signatures have the attributes of the builder.
+    fn visit_block_mut(&mut self, _: &mut syn::Block) {}
+}
@@ -2,9 +2,11 @@
 
 use proc_macro2::TokenStream;
 use quote::quote;
-use syn::{parse::Error, parse_macro_input, spanned::Spanned, DeriveInput};
+use syn::{parse::Error, parse_macro_input, spanned::Spanned, DeriveInput, ItemFn, ItemImpl};
 
 mod field_info;
+mod flatten;
+mod fn_info;
 mod mutator;
 mod struct_info;
 mod util;
@@ -18,42 +20,143 @@ pub fn derive_typed_builder(input: proc_macro::TokenStream) -> proc_macro::Token
     }
 }
 
+#[proc_macro_attribute]
+pub fn builder_fn(args: proc_macro::TokenStream, input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as ItemFn);
+    match fn_info::impl_builder_fn(args.into(), input.clone()) {
+        Ok(output) => output.into(),
+        Err(error) => fn_info::original_item_with_error(error, input.into()).into(),
+    }
+}
+
+#[proc_macro_attribute]
+pub fn builder_impl(args: proc_macro::TokenStream, input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as ItemImpl);
+    match fn_info::impl_builder_impl(args.into(), input.clone()) {
+        Ok(output) => output.into(),
+        Err(error) => fn_info::original_item_with_error(error, input.into()).into(),
+    }
+}
+
+#[doc(hidden)]
+#[proc_macro]
+pub fn __flatten_builder(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    match flatten::impl_flatten_builder(input.into()) {
+        Ok(output) => output.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
 fn impl_my_derive(ast: &syn::DeriveInput) -> Result<TokenStream, Error> {
     let data = match &ast.data {
         syn::Data::Struct(data) => match &data.fields {
-            syn::Fields::Named(fields) => {
-                let struct_info = struct_info::StructInfo::new(ast, fields.named.iter())?;
-                let builder_creation = struct_info.builder_creation_impl()?;
-                let fields = struct_info
-                    .setter_fields()
-                    .map(|f| struct_info.field_impl(f))
-                    .collect::<Result<TokenStream, _>>()?;
-                let required_fields = struct_info
-                    .setter_fields()
-                    .filter(|f| f.builder_attr.default.is_none())
-                    .map(|f| struct_info.required_field_impl(f));
-                let mutators = struct_info
-                    .fields
-                    .iter()
-                    .flat_map(|f| &f.builder_attr.mutators)
-                    .chain(&struct_info.builder_attr.mutators)
-                    .map(|m| struct_info.mutator_impl(m))
-                    .collect::<Result<TokenStream, _>>()?;
-                let build_method = struct_info.build_method_impl();
-
-                quote! {
-                    #builder_creation
-                    #fields
-                    #(#required_fields)*
-                    #mutators
-                    #build_method
+            syn::Fields::Named(syn::FieldsNamed { named: fields, .. })
+            | syn::Fields::Unnamed(syn::FieldsUnnamed { unnamed: fields, .. }) => {
+                let mut struct_info = struct_info::StructInfo::new(ast, fields.iter())?;
+                if struct_info.flattened_fields().next().is_some() {
+                    flatten::resolve_flattened_fields(ast, &mut struct_info, Vec::new())?
+                } else {
+                    impl_builder(&struct_info)?
                 }
             }
-            syn::Fields::Unnamed(_) => return Err(Error::new(ast.span(), "TypedBuilder is not supported for tuple structs")),
             syn::Fields::Unit => return Err(Error::new(ast.span(), "TypedBuilder is not supported for unit structs")),
         },
-        syn::Data::Enum(_) => return Err(Error::new(ast.span(), "TypedBuilder is not supported for enums")),
+        syn::Data::Enum(data) => {
+            let enum_attr = struct_info::TypeBuilderAttr::new(&ast.attrs)?;
+            if let Some(name) = &enum_attr.builder_type.common.name {
+                return Err(Error::new_spanned(
+                    name,
+                    "the builder type of an enum must be named separately on each variant",
+                ));
+            }
+            if let Some(module) = &enum_attr.builder_type.module {
+                return Err(Error::new_spanned(
+                    module,
+                    "the module of the builder of an enum must be named separately on each variant",
+                ));
+            }
+            if let Some(name) = &enum_attr.builder_method.common.name {
+                return Err(Error::new_spanned(
+                    name,
+                    "the builder method of an enum must be named separately on each variant",
+                ));
+            }
+            data.variants
+                .iter()
+                .filter_map(|variant| match variant.fields {
+                    syn::Fields::Named(_) | syn::Fields::Unnamed(_) => Some(
+                        struct_info::StructInfo::new_for_variant(ast, variant).and_then(|struct_info| {
+                            if let Some(field) = struct_info.flattened_fields().next() {
+                                return Err(Error::new(
+                                    field.builder_attr.flatten.unwrap_or_else(|| field.name.span()),
+                                    "flatten is only supported in structs",
+                                ));
+                            }
+                            impl_builder(&struct_info)
+                        }),
+                    ),
+                    syn::Fields::Unit => {
+                        let attr = variant.attrs.iter().find(|attr| attr.path().is_ident("builder"))?;
+                        Some(Err(Error::new_spanned(
+                            attr,
+                            "TypedBuilder is not supported for unit variants",
+                        )))
+                    }
+                })
+                .collect::<Result<TokenStream, _>>()?
+        }
         syn::Data::Union(_) => return Err(Error::new(ast.span(), "TypedBuilder is not supported for unions")),
     };
     Ok(data)
 }
+
+fn impl_builder(struct_info: &struct_info::StructInfo<'_>) -> Result<TokenStream, Error> {
+    let builder_creation = struct_info.builder_creation_impl()?;
+    let prefilled_builder = struct_info.prefilled_builder_impl();
+    let fields = struct_info
+        .setter_fields()
+        .map(|f| struct_info.field_impl(f))
+        .collect::<Result<TokenStream, _>>()?;
+    let unset_fields = struct_info
+        .setter_fields()
+        .map(|f| struct_info.unset_field_impl(f))
+        .collect::<TokenStream>();
+    let getters = struct_info
+        .included_fields()
+        .filter(|f| {
+            f.builder_attr.flatten.is_none()
+                && (f.builder_attr.via_mutators.is_some() || f.builder_attr.setter.getter.is_some())
+                && !struct_info.is_receiver(f)
+        })
+        .map(|f| struct_info.getter_impl(f))
+        .collect::<TokenStream>();
+    let builder_module = struct_info.builder_module_impl();
+    let mutators = struct_info
+        .fields
+        .iter()
+        .flat_map(|f| &f.builder_attr.mutators)
+        .chain(&struct_info.builder_attr.mutators)
+        .map(|m| struct_info.mutator_impl(m))
+        .collect::<Result<TokenStream, _>>()?;
+    let flattened_fields = struct_info
+        .flattened_fields()
+        .map(|f| struct_info.flattened_field_impl(f))
+        .collect::<TokenStream>();
+    let build_method = struct_info.build_method_impl();
+    let runtime_builder = struct_info.runtime_builder_impl()?;
+    let flatten_macro = struct_info.flatten_macro_impl();
+
+    Ok(quote! {
+        #builder_creation
+        #prefilled_builder
+        #fields
+        #unset_fields
+        #getters
+        #flattened_fields
+        #builder_module
+        #mutators
+        #build_method
+        #runtime_builder
+        #flatten_macro
+    })
+}
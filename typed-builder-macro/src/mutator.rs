@@ -15,21 +15,28 @@ use crate::util::{pat_to_ident, ApplyMeta, AttrArg};
 pub struct Mutator {
     pub fun: ItemFn,
     pub required_fields: HashSet<Ident>,
+    pub set_fields: HashSet<Ident>,
+    /// The return type of a fallible mutator - `Result<(), E>`, or an alias of `Result` whose
+    /// first generic argument is `()`, such as `io::Result<()>`.
+    pub result: Option<syn::TypePath>,
 }
 
 #[derive(Default)]
 struct MutatorAttribute {
     requires: HashSet<Ident>,
+    sets: HashSet<Ident>,
 }
 
 impl ApplyMeta for MutatorAttribute {
     fn apply_meta(&mut self, expr: AttrArg) -> Result<(), Error> {
-        if expr.name() != "requires" {
-            return Err(Error::new_spanned(expr.name(), "Only `requires` is supported"));
-        }
+        let fields = match expr.name().to_string().as_str() {
+            "requires" => &mut self.requires,
+            "sets" => &mut self.sets,
+            _ => return Err(Error::new_spanned(expr.name(), "Only `requires` and `sets` are supported")),
+        };
 
         match expr.key_value()?.parse_value()? {
-            Expr::Array(syn::ExprArray { elems, .. }) => self.requires.extend(
+            Expr::Array(syn::ExprArray { elems, .. }) => fields.extend(
                 elems
                     .into_iter()
                     .map(|expr| match expr {
@@ -70,7 +77,10 @@ impl Parse for Mutator {
 
         // Ensure `&mut self` receiver
         if let Some(FnArg::Receiver(receiver)) = fun.sig.inputs.first_mut() {
-            *receiver = parse_quote!(&mut self);
+            // Reuse the `self` token, so that it has the same hygiene as the `self` in the body -
+            // which matters when the mutator passes through a `macro_rules!` macro (see `flatten`).
+            let self_token = receiver.self_token;
+            *receiver = parse_quote!(&mut #self_token);
         } else {
             // Error either on first argument or `()`
             return Err(syn::Error::new(
@@ -83,17 +93,56 @@ impl Parse for Mutator {
             ));
         };
 
+        let result = match &fun.sig.output {
+            ReturnType::Default => None,
+            ReturnType::Type(_, ty) if matches!(&**ty, Type::Tuple(unit) if unit.elems.is_empty()) => None,
+            ReturnType::Type(_, ty) => Some(
+                unit_result_type(ty).ok_or_else(|| Error::new_spanned(ty, "mutators can only return `()` or `Result<(), E>`"))?,
+            ),
+        };
+
         Ok(Self {
             fun,
             required_fields: attribute.requires,
+            set_fields: attribute.sets,
+            result,
         })
     }
 }
 
+/// The type itself, if it's a path to a `Result` whose first generic argument is `()` - which also
+/// covers the aliases that fix the error type, such as `io::Result<()>`.
+fn unit_result_type(ty: &Type) -> Option<syn::TypePath> {
+    let Type::Path(path @ syn::TypePath { qself: None, .. }) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last().filter(|segment| segment.ident == "Result")?;
+    let syn::PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    match args.args.first()? {
+        syn::GenericArgument::Type(Type::Tuple(ok)) if ok.elems.is_empty() => Some(path.clone()),
+        _ => None,
+    }
+}
+
 impl Mutator {
     /// Signature for Builder::<mutator> function
     pub fn outer_sig(&self, output: Type) -> Signature {
         let mut sig = self.fun.sig.clone();
+        // A fallible mutator's method returns the same `Result`, with the builder instead of `()`.
+        let output = match &self.result {
+            Some(result) => {
+                let mut result = result.clone();
+                if let Some(syn::PathArguments::AngleBracketed(args)) =
+                    result.path.segments.last_mut().map(|segment| &mut segment.arguments)
+                {
+                    args.args[0] = syn::GenericArgument::Type(output);
+                }
+                Type::Path(result)
+            }
+            None => output,
+        };
         sig.output = ReturnType::Type(Default::default(), output.into());
 
         sig.inputs = sig
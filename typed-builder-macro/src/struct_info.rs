@@ -1,36 +1,235 @@
-use std::cell::OnceCell;
-use std::rc::Rc;
+use std::collections::HashMap;
 
-use proc_macro2::{Ident, Span, TokenStream};
+use proc_macro2::{Ident, TokenStream};
 use quote::{format_ident, quote, quote_spanned, ToTokens};
+use syn::parse::Parser;
 use syn::punctuated::Punctuated;
-use syn::{parse_quote, Error, GenericArgument, ItemFn, Token};
+use syn::{parse_quote, parse_quote_spanned, Error, GenericArgument, ItemFn, Token};
 
 use crate::field_info::{FieldBuilderAttr, FieldInfo};
+use crate::flatten::FlattenedSetter;
+use crate::fn_info::MethodInfo;
 use crate::mutator::Mutator;
 use crate::util::{
-    empty_type, empty_type_tuple, first_visibility, modify_types_generics_hack, path_to_single_string, public_visibility,
-    strip_raw_ident_prefix, type_tuple, ApplyMeta, AttrArg,
+    empty_type_tuple, first_visibility, ident_to_type, modify_types_generics_hack, outlives_bounds, path_to_single_string,
+    public_visibility, strip_raw_ident_prefix, to_pascal_case, to_snake_case, type_tuple, type_uses_generics, variable_idents,
+    ApplyMeta, AttrArg, SubAttr,
 };
 
 #[derive(Debug)]
 pub struct StructInfo<'a> {
     pub vis: &'a syn::Visibility,
     pub name: &'a syn::Ident,
+    pub target: BuilderTarget<'a>,
     pub generics: &'a syn::Generics,
     pub fields: Box<[FieldInfo<'a>]>,
+    /// The indices of the fields in the order their values are resolved in by the build method.
+    pub resolution_order: Box<[usize]>,
 
     pub builder_attr: TypeBuilderAttr<'a>,
     pub builder_name: syn::Ident,
-    pub core: syn::Ident,
+}
+
+/// What the build method of the builder creates.
+#[derive(Debug)]
+pub enum BuilderTarget<'a> {
+    /// An instance of the struct.
+    Struct,
+    /// An instance of the enum, using the specified variant.
+    Variant(&'a syn::Ident),
+    /// The result of calling the function, whose arguments are the fields of the builder.
+    Function {
+        /// The original function, stripped of its documentation and of the builder attributes.
+        item: &'a syn::ItemFn,
+        /// The documentation of the original function.
+        doc: &'a [syn::Attribute],
+        /// The return type of the function, with the elided lifetimes filled in.
+        output: &'a syn::Type,
+        /// When the function is a method - the `impl` block it is defined in.
+        method: Option<&'a MethodInfo>,
+    },
+}
+
+impl BuilderTarget<'_> {
+    /// Whether the builder calls a method with a receiver, which is held as its first field.
+    fn has_receiver(&self) -> bool {
+        matches!(
+            self,
+            BuilderTarget::Function {
+                method: Some(MethodInfo { receiver: Some(_), .. }),
+                ..
+            }
+        )
+    }
 }
 
 impl<'a> StructInfo<'a> {
-    pub fn included_fields(&self) -> impl Iterator<Item = &FieldInfo<'a>> {
+    pub fn included_fields(&self) -> impl DoubleEndedIterator<Item = &FieldInfo<'a>> {
         self.fields.iter().filter(|f| f.builder_attr.setter.skip.is_none())
     }
-    pub fn setter_fields(&self) -> impl Iterator<Item = &FieldInfo<'a>> {
-        self.included_fields().filter(|f| f.builder_attr.via_mutators.is_none())
+    pub fn setter_fields(&self) -> impl DoubleEndedIterator<Item = &FieldInfo<'a>> {
+        self.included_fields()
+            .filter(|f| f.builder_attr.via_mutators.is_none() && f.builder_attr.flatten.is_none())
+    }
+    pub fn flattened_fields(&self) -> impl Iterator<Item = &FieldInfo<'a>> {
+        self.included_fields().filter(|f| f.builder_attr.flatten.is_some())
+    }
+
+    /// Whether the field holds the receiver of a method, which the builder gets when it's created.
+    pub fn is_receiver(&self, field: &FieldInfo<'_>) -> bool {
+        self.target.has_receiver() && field.ordinal == 0
+    }
+
+    /// The state of a field that is set.
+    fn set_state_type(&self, field: &FieldInfo<'_>) -> syn::Type {
+        let crate_module_path = &self.builder_attr.crate_module_path;
+        let ty = field.ty;
+        parse_quote!(#crate_module_path::state::Set<#ty>)
+    }
+
+    /// Whether the builder method takes the value of the field as an argument.
+    fn is_builder_method_arg(&self, field: &FieldInfo<'_>) -> bool {
+        self.builder_attr.builder_method.args.contains(&field.name)
+    }
+
+    /// The parameters of the builder method, which are those of the setters of its arguments, and the
+    /// statements that make the values of the fields from them - along with whether these can be
+    /// evaluated in a `const fn`.
+    fn builder_method_params(&self) -> syn::Result<(Vec<TokenStream>, TokenStream, bool)> {
+        let mut params = Vec::new();
+        let mut conversions = TokenStream::new();
+        let mut is_const = true;
+        for arg in &self.builder_attr.builder_method.args {
+            let field = self.fields.iter().find(|f| f.name == *arg).unwrap();
+            let SetterArgs {
+                params: field_params,
+                value,
+                ..
+            } = self.setter_args(field)?;
+            params.push(field_params);
+            let setter = &field.builder_attr.setter;
+            if setter.auto_into.is_some() || setter.transform.is_some() || setter.strip_option.is_some() {
+                conversions.extend(quote!(let #arg = #value;));
+            }
+            is_const &= setter.auto_into.is_none() && setter.transform.is_none();
+        }
+        Ok((params, conversions, is_const))
+    }
+
+    /// The state of the builder when it is created.
+    fn empty_state_type(&self) -> syn::TypeTuple {
+        self.initial_state_type(None)
+    }
+
+    /// The state of the builder when it is created, with the fields of the preset prefilled.
+    fn initial_state_type(&self, preset: Option<&Preset>) -> syn::TypeTuple {
+        let crate_module_path = &self.builder_attr.crate_module_path;
+        type_tuple(self.included_fields().map(|f| {
+            if preset.is_some_and(|preset| preset.value_of(f).is_some()) {
+                let ty = f.ty;
+                parse_quote!(#crate_module_path::state::Prefilled<#ty>)
+            } else if f.builder_attr.via_mutators.is_some() || self.is_builder_method_arg(f) {
+                self.set_state_type(f)
+            } else if f.builder_attr.flatten.is_some() {
+                let ty = f.ty;
+                parse_quote!(<#ty as #crate_module_path::HasBuilder>::Builder)
+            } else {
+                parse_quote!(#crate_module_path::state::Unset)
+            }
+        }))
+    }
+
+    /// The generics of the builder's methods that are available in every state that implements
+    /// the traits of some of its fields, with the whole state as a single parameter, and the
+    /// generic arguments of the builder in that state.
+    fn any_state_generics(&self) -> (syn::Generics, Punctuated<GenericArgument, Token![,]>) {
+        let state: syn::Ident = parse_quote!(TypedBuilderFields);
+        let mut generics = self.generics.clone();
+        generics.params.push(syn::GenericParam::Type(state.clone().into()));
+        let mut ty_generics = self.generic_arguments();
+        ty_generics.push(syn::GenericArgument::Type(ident_to_type(state)));
+        (generics, ty_generics)
+    }
+
+    /// With `ordered`, the bounds on the state of the builder for the setters of the field to be
+    /// available: the required fields declared before it must be set, and a field with a default
+    /// can only be set after all the required fields are.
+    fn ordered_setter_bounds(&self, field: &FieldInfo<'_>, state: &syn::Type) -> Vec<syn::WherePredicate> {
+        self.ordered_set_bounds(&[field], state)
+    }
+
+    /// With `ordered`, the bounds on the state of the builder for setting all the given fields at
+    /// once: the required fields that any of them must be set after, except for the given fields
+    /// themselves, must be set.
+    fn ordered_set_bounds(&self, fields: &[&FieldInfo<'_>], state: &syn::Type) -> Vec<syn::WherePredicate> {
+        if self.builder_attr.ordered.is_none() {
+            return Vec::new();
+        }
+        let builder_module_name = self.builder_module_name();
+        let last = fields
+            .iter()
+            .map(|field| self.required_fields().take_while(|f| f.ordinal != field.ordinal).count())
+            .max()
+            .unwrap_or(0);
+        self.required_fields()
+            .take(last)
+            .filter(|f| fields.iter().all(|field| field.ordinal != f.ordinal))
+            .map(|f| {
+                let is_set_trait = f.is_set_trait_name();
+                parse_quote!(#state: #builder_module_name::#is_set_trait)
+            })
+            .collect()
+    }
+
+    /// With `ordered`, the states of the builder in which the setters of the field are available - as
+    /// the generic parameters and the state of each `impl` block that defines them, so that the
+    /// setters of the fields that come later don't exist before their turn. The states fix the
+    /// required field declared before the field (or, for a field with a default, the last required
+    /// field) to be set, which means that all the required fields before it are - since it could
+    /// only be set after them. `None` when the setters are available in any state.
+    fn ordered_setter_states(&self, field: &FieldInfo<'_>) -> Option<Vec<(syn::Generics, syn::TypeTuple)>> {
+        self.builder_attr.ordered?;
+        let previous = self.required_fields().take_while(|f| f.ordinal != field.ordinal).last()?;
+        let crate_module_path = &self.builder_attr.crate_module_path;
+        let previous_type = previous.ty;
+        let previous_states: [syn::Type; 2] = [
+            parse_quote!(#crate_module_path::state::Set<#previous_type>),
+            parse_quote!(#crate_module_path::state::Prefilled<#previous_type>),
+        ];
+        let states = previous_states
+            .into_iter()
+            .map(|previous_state| {
+                let mut generics = self.generics.clone();
+                let mut state = empty_type_tuple();
+                for f in self.included_fields() {
+                    if f.ordinal == previous.ordinal {
+                        state.elems.push(previous_state.clone());
+                    } else {
+                        generics.params.push(f.generic_ty_param());
+                        state.elems.push(f.type_ident());
+                    }
+                }
+                (generics, state)
+            })
+            .collect();
+        Some(states)
+    }
+
+    /// With `ordered`, the bounds on the state of the builder for a required field to be unset - the
+    /// required fields declared after it must be unset, so that it can be set again.
+    fn ordered_unset_bounds(&self, field: &FieldInfo<'_>, state: &syn::Type) -> Vec<syn::WherePredicate> {
+        if self.builder_attr.ordered.is_none() || field.builder_attr.default.is_some() {
+            return Vec::new();
+        }
+        let builder_module_name = self.builder_module_name();
+        self.required_fields()
+            .skip_while(|f| f.ordinal != field.ordinal)
+            .skip(1)
+            .map(|f| {
+                let is_unset_trait = f.is_unset_trait_name();
+                parse_quote!(#state: #builder_module_name::#is_unset_trait)
+            })
+            .collect()
     }
 
     pub fn generic_arguments(&self) -> Punctuated<GenericArgument, Token![,]> {
@@ -52,24 +251,427 @@ impl<'a> StructInfo<'a> {
     }
 
     pub fn new(ast: &'a syn::DeriveInput, fields: impl Iterator<Item = &'a syn::Field>) -> syn::Result<Self> {
-        let builder_attr = TypeBuilderAttr::new(&ast.attrs)?;
+        Self::new_impl(
+            &ast.vis,
+            &ast.ident,
+            BuilderTarget::Struct,
+            &ast.generics,
+            TypeBuilderAttr::new(&ast.attrs)?,
+            fields,
+        )
+    }
+
+    pub fn new_for_variant(ast: &'a syn::DeriveInput, variant: &'a syn::Variant) -> syn::Result<Self> {
+        Self::new_impl(
+            &ast.vis,
+            &ast.ident,
+            BuilderTarget::Variant(&variant.ident),
+            &ast.generics,
+            TypeBuilderAttr::new(ast.attrs.iter().chain(&variant.attrs))?,
+            variant.fields.iter(),
+        )
+    }
+
+    pub fn new_impl(
+        vis: &'a syn::Visibility,
+        name: &'a syn::Ident,
+        target: BuilderTarget<'a>,
+        generics: &'a syn::Generics,
+        builder_attr: TypeBuilderAttr<'a>,
+        fields: impl Iterator<Item = &'a syn::Field>,
+    ) -> syn::Result<Self> {
+        let has_receiver = target.has_receiver();
+        let fields = fields
+            .enumerate()
+            .map(|(i, f)| {
+                let field_defaults = if has_receiver && i == 0 {
+                    FieldBuilderAttr::default()
+                } else {
+                    builder_attr.field_defaults.clone()
+                };
+                FieldInfo::new(i, f, field_defaults)
+            })
+            .collect::<Result<Box<[_]>, _>>()?;
+        builder_attr
+            .build_method
+            .check_validation(fields.iter().filter_map(|f| f.builder_attr.validate.as_ref()))?;
+        if let (BuilderTarget::Function { .. }, Some(validate)) = (&target, &builder_attr.build_method.validate) {
+            return Err(Error::new_spanned(
+                validate,
+                "`build_method(validate = ...)` is not supported for functions - validate the arguments instead",
+            ));
+        }
+        if let Some(runtime) = builder_attr.runtime {
+            if let BuilderTarget::Function { .. } = target {
+                return Err(Error::new(runtime, "`runtime` is not supported for functions"));
+            }
+            if let Some(field) = fields.iter().find(|f| f.builder_attr.flatten.is_some()) {
+                return Err(Error::new(
+                    field.builder_attr.flatten.unwrap_or(runtime),
+                    "flattened fields are not supported by the runtime builder",
+                ));
+            }
+        }
+        for (i, arg) in builder_attr.builder_method.args.iter().enumerate() {
+            if builder_attr.builder_method.args[..i].contains(arg) {
+                return Err(Error::new_spanned(
+                    arg,
+                    "the field is already an argument of the builder method",
+                ));
+            }
+            let Some(field) = fields.iter().find(|f| f.name == *arg) else {
+                return Err(Error::new_spanned(arg, format!("no field named `{}`", arg)));
+            };
+            if field.builder_attr.setter.skip.is_some()
+                || field.builder_attr.via_mutators.is_some()
+                || field.builder_attr.flatten.is_some()
+            {
+                return Err(Error::new_spanned(
+                    arg,
+                    "only fields with setters can be arguments of the builder method",
+                ));
+            }
+            if field.builder_attr.setter.nested.is_some()
+                || field.builder_attr.setter.each.is_some()
+                || field.builder_attr.setter.strip_bool.is_some()
+            {
+                return Err(Error::new_spanned(
+                    arg,
+                    "fields whose setter is `nested`, `each` or `strip_bool` cannot be arguments of the builder method",
+                ));
+            }
+        }
+        for (i, preset) in builder_attr.presets.iter().enumerate() {
+            if !matches!(target, BuilderTarget::Struct) {
+                return Err(Error::new_spanned(&preset.name, "presets are only supported for structs"));
+            }
+            if builder_attr.presets[..i].iter().any(|other| other.name == preset.name) {
+                return Err(Error::new_spanned(&preset.name, "duplicate preset"));
+            }
+            for (j, (name, _)) in preset.fields.iter().enumerate() {
+                if preset.fields[..j].iter().any(|(other, _)| other == name) {
+                    return Err(Error::new_spanned(name, "the field is already set by the preset"));
+                }
+                let Some(field) = fields.iter().find(|f| f.name == *name) else {
+                    return Err(Error::new_spanned(name, format!("no field named `{}`", name)));
+                };
+                if field.builder_attr.setter.skip.is_some()
+                    || field.builder_attr.via_mutators.is_some()
+                    || field.builder_attr.flatten.is_some()
+                {
+                    return Err(Error::new_spanned(name, "only fields with setters can be set by presets"));
+                }
+                if builder_attr.builder_method.args.contains(name) {
+                    return Err(Error::new_spanned(
+                        name,
+                        "the field is an argument of the builder method, so presets cannot set it",
+                    ));
+                }
+            }
+        }
+        if let Some(ordered) = builder_attr.ordered {
+            if let Some(field) = fields.iter().find(|f| f.builder_attr.flatten.is_some()) {
+                return Err(Error::new(
+                    field.builder_attr.flatten.unwrap_or(ordered),
+                    "flattened fields are not supported by `ordered`",
+                ));
+            }
+        }
+        if let Some(stable) = builder_attr.builder_type.stable {
+            if let Some(field) = fields.iter().find(|f| f.builder_attr.flatten.is_some()) {
+                return Err(Error::new(
+                    field.builder_attr.flatten.unwrap_or(stable),
+                    "flattened fields are not supported by `builder_type(stable)`",
+                ));
+            }
+        }
         let builder_name = builder_attr
             .builder_type
+            .common
             .get_name()
             .map(|name| strip_raw_ident_prefix(name.to_string()))
-            .unwrap_or_else(|| strip_raw_ident_prefix(format!("{}Builder", ast.ident)));
-        Ok(StructInfo {
-            vis: &ast.vis,
-            name: &ast.ident,
-            generics: &ast.generics,
-            fields: fields
-                .enumerate()
-                .map(|(i, f)| FieldInfo::new(i, f, builder_attr.field_defaults.clone()))
-                .collect::<Result<_, _>>()?,
+            .unwrap_or_else(|| match target {
+                BuilderTarget::Struct => strip_raw_ident_prefix(format!("{}Builder", name)),
+                BuilderTarget::Variant(variant) => {
+                    strip_raw_ident_prefix(format!("{}{}Builder", name, strip_raw_ident_prefix(variant.to_string())))
+                }
+                BuilderTarget::Function {
+                    method: Some(MethodInfo { self_name, .. }),
+                    ..
+                } => {
+                    if name == "new" {
+                        strip_raw_ident_prefix(format!("{}Builder", self_name))
+                    } else {
+                        strip_raw_ident_prefix(format!(
+                            "{}{}Builder",
+                            self_name,
+                            to_pascal_case(&strip_raw_ident_prefix(name.to_string()))
+                        ))
+                    }
+                }
+                BuilderTarget::Function { method: None, .. } => {
+                    format!("{}Builder", to_pascal_case(&strip_raw_ident_prefix(name.to_string())))
+                }
+            });
+        let resolution_order = Self::resolution_order(&fields)?;
+        let struct_info = StructInfo {
+            vis,
+            name,
+            target,
+            generics,
+            fields,
+            resolution_order,
             builder_attr,
             builder_name: syn::Ident::new(&builder_name, proc_macro2::Span::call_site()),
-            core: syn::Ident::new(&format!("{}_core", builder_name), proc_macro2::Span::call_site()),
-        })
+        };
+        if let Some(flattenable) = struct_info.builder_attr.flattenable {
+            if !struct_info.can_be_flattened() {
+                return Err(Error::new(
+                    flattenable,
+                    "only structs whose builder method takes no arguments, and whose builder method, builder type and build method keep their default visibility, can be `flattenable`",
+                ));
+            }
+        }
+        if let Some(into_builder) = struct_info.builder_attr.into_builder {
+            if !matches!(struct_info.target, BuilderTarget::Struct) {
+                return Err(Error::new(into_builder, "`into_builder` is only supported for structs"));
+            }
+        }
+        Ok(struct_info)
+    }
+
+    /// The order in which the build method resolves the values of the fields. The default of a field
+    /// can refer to other fields, so it comes after them - and otherwise the fields keep the order
+    /// they are declared in.
+    fn resolution_order(fields: &[FieldInfo<'_>]) -> syn::Result<Box<[usize]>> {
+        let dependencies = fields
+            .iter()
+            .map(|field| {
+                let Some(default) = &field.builder_attr.default else {
+                    return Vec::new();
+                };
+                let idents = variable_idents(default);
+                fields
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, other)| other.ordinal != field.ordinal && idents.contains(&other.name))
+                    .map(|(i, _)| i)
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        let mut order = Vec::with_capacity(fields.len());
+        let mut resolved = vec![false; fields.len()];
+        while order.len() < fields.len() {
+            let is_ready = |i: usize| !resolved[i] && dependencies[i].iter().all(|&dependency| resolved[dependency]);
+            if let Some(next) = (0..fields.len()).find(|&i| is_ready(i)) {
+                resolved[next] = true;
+                order.push(next);
+                continue;
+            }
+            // Every unresolved field depends on another unresolved field, so following these
+            // dependencies from any of them must lead to a cycle.
+            let mut path = vec![(0..fields.len()).find(|&i| !resolved[i]).unwrap()];
+            let cycle_start = loop {
+                let last = *path.last().unwrap();
+                let next = *dependencies[last].iter().find(|&&dependency| !resolved[dependency]).unwrap();
+                if let Some(start) = path.iter().position(|&i| i == next) {
+                    path.push(next);
+                    break start;
+                }
+                path.push(next);
+            };
+            let cycle = &path[cycle_start..];
+            let names = cycle
+                .iter()
+                .map(|&i| format!("`{}`", strip_raw_ident_prefix(fields[i].name.to_string())))
+                .collect::<Vec<_>>();
+            return Err(Error::new_spanned(
+                fields[cycle[0]].builder_attr.default.as_ref(),
+                format!("the defaults of these fields depend on each other: {}", names.join(" -> ")),
+            ));
+        }
+        Ok(order.into())
+    }
+
+    /// Whether the struct implements `HasBuilder`, which would expose the builder method and type
+    /// wherever the struct is visible - so it's only done when they are not restricted further. The
+    /// builder method of the trait also can't take arguments.
+    fn has_builder_trait(&self) -> bool {
+        matches!(self.target, BuilderTarget::Struct)
+            && self.builder_attr.builder_method.common.vis.is_none()
+            && self.builder_attr.builder_method.args.is_empty()
+            && self.builder_attr.builder_type.common.vis.is_none()
+    }
+
+    /// Whether the struct implements `IntoBuilder`, whose builder type names the types of the fields.
+    /// These may be less visible than the struct when the fields are, which would make the
+    /// implementation leak them - so it is only done when the fields are as visible as the struct.
+    fn has_into_builder_trait(&self) -> bool {
+        let struct_vis = self.vis.to_token_stream().to_string();
+        self.has_builder_trait()
+            && (matches!(self.vis, syn::Visibility::Inherited)
+                || self
+                    .included_fields()
+                    .all(|f| matches!(f.vis, syn::Visibility::Public(_)) || f.vis.to_token_stream().to_string() == struct_vis))
+    }
+
+    /// Whether other structs can flatten this one, which requires it to implement `HasBuilder` and
+    /// its builder to implement `Build`.
+    fn can_be_flattened(&self) -> bool {
+        self.has_builder_trait() && self.builder_attr.build_method.common.vis.is_none()
+    }
+
+    /// Whether to generate what other structs need for flattening this one - which is only done on
+    /// request, since it adds a macro and a `FlattenSetter` implementation for each setter.
+    fn is_flattenable(&self) -> bool {
+        self.builder_attr.flattenable.is_some() && self.can_be_flattened()
+    }
+
+    /// The setters the builder exposes to the builders of structs that flatten this one, in the
+    /// order of their indices.
+    pub fn flattened_setters(&self) -> Vec<FlattenedSetter> {
+        self.included_fields()
+            .flat_map(|field| -> Vec<FlattenedSetter> {
+                let setter = |name: Ident, arity| FlattenedSetter {
+                    name,
+                    field: field.name.clone(),
+                    arity,
+                };
+                let settings = &field.builder_attr.setter;
+                if field.builder_attr.via_mutators.is_some() {
+                    Vec::new()
+                } else if field.builder_attr.flatten.is_some() {
+                    field.flattened_setters.clone()
+                } else if let Some(each) = &settings.each {
+                    vec![setter(field.setter_method_name(), Some(1)), setter(each.clone(), Some(1))]
+                } else if settings.nested.is_some() {
+                    vec![setter(field.setter_method_name(), None)]
+                } else if settings.strip_bool.is_some() {
+                    vec![setter(field.setter_method_name(), Some(0))]
+                } else if let Some(transform) = &settings.transform {
+                    vec![setter(field.setter_method_name(), Some(transform.params.len()))]
+                } else {
+                    vec![setter(field.setter_method_name(), Some(1))]
+                }
+            })
+            .collect()
+    }
+
+    /// The index of the `FlattenSetter` implementation of the first setter of the field.
+    fn flatten_index(&self, field: &FieldInfo<'_>) -> usize {
+        self.included_fields()
+            .take_while(|f| f.ordinal != field.ordinal)
+            .map(|f| {
+                if f.builder_attr.via_mutators.is_some() {
+                    0
+                } else if f.builder_attr.flatten.is_some() {
+                    f.flattened_setters.len()
+                } else if f.builder_attr.setter.each.is_some() {
+                    2
+                } else {
+                    1
+                }
+            })
+            .sum()
+    }
+
+    /// Make sure the setters of the flattened fields can be added to the builder.
+    pub fn check_flattened_setters(&self) -> syn::Result<()> {
+        let mut setters = HashMap::new();
+        for field in self.setter_fields() {
+            setters.insert(field.setter_method_name().to_string(), field.name.clone());
+            if let Some(each) = &field.builder_attr.setter.each {
+                setters.insert(each.to_string(), field.name.clone());
+            }
+        }
+        for field in self.flattened_fields() {
+            let Some(flatten) = field.builder_attr.flatten else {
+                continue;
+            };
+            for setter in &field.flattened_setters {
+                if setter.arity.is_none() {
+                    let mut error = Error::new(
+                        flatten,
+                        format_args!("cannot flatten the nested setter of `{}`", setter.field),
+                    );
+                    error.combine(Error::new(setter.field.span(), "nested setter defined here"));
+                    return Err(error);
+                }
+                if let Some(other) = setters.insert(setter.name.to_string(), setter.field.clone()) {
+                    let mut error = Error::new(
+                        setter.field.span(),
+                        format_args!(
+                            "the setter `{}` of the flattened field `{}` clashes with the setter of `{}`",
+                            setter.name, field.name, other
+                        ),
+                    );
+                    error.combine(Error::new(
+                        other.span(),
+                        format_args!("the setter `{}` is also defined here", setter.name),
+                    ));
+                    return Err(error);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The name of what the builder builds, for use in generated documentation.
+    fn built_name(&self) -> String {
+        match self.target {
+            BuilderTarget::Variant(variant) => format!("{}::{}", self.name, variant),
+            BuilderTarget::Function {
+                method: Some(MethodInfo { self_name, .. }),
+                ..
+            } => format!("{}::{}", self_name, self.name),
+            _ => self.name.to_string(),
+        }
+    }
+
+    fn builder_method_name(&self) -> TokenStream {
+        self.builder_attr
+            .builder_method
+            .common
+            .get_name()
+            .unwrap_or_else(|| match self.target {
+                BuilderTarget::Struct => quote!(builder),
+                BuilderTarget::Variant(variant) => {
+                    format_ident!("{}_builder", to_snake_case(&strip_raw_ident_prefix(variant.to_string()))).into_token_stream()
+                }
+                BuilderTarget::Function { method: Some(_), .. } if self.name == "new" => quote!(builder),
+                BuilderTarget::Function { .. } => self.name.to_token_stream(),
+            })
+    }
+
+    /// The types that the `PhantomData` of the builder holds, so that it uses all the generic
+    /// parameters.
+    fn phantom_generics(&self) -> Vec<TokenStream> {
+        let phantom_generics = self.generics.params.iter().filter_map(|param| match param {
+            syn::GenericParam::Lifetime(lifetime) => {
+                let lifetime = &lifetime.lifetime;
+                Some(quote!(&#lifetime ()))
+            }
+            syn::GenericParam::Type(ty) => {
+                let ty = &ty.ident;
+                Some(ty.to_token_stream())
+            }
+            syn::GenericParam::Const(_cnst) => None,
+        });
+        // The bounds of the builder's methods may rely on bounds implied by the types of the fields
+        // that use the generics (e.g. `T: 'a` for `&'a T`). Mentioning these types in the builder
+        // makes the builder imply the bounds as well, and putting them in a function pointer keeps
+        // them from affecting its auto traits.
+        let field_types = self
+            .fields
+            .iter()
+            .map(|field| field.ty)
+            .filter(|ty| type_uses_generics(ty, self.generics))
+            .collect::<Vec<_>>();
+        if field_types.is_empty() {
+            return phantom_generics.collect();
+        }
+        phantom_generics.chain(Some(quote!(fn() -> (#( #field_types, )*)))).collect()
     }
 
     pub fn builder_creation_impl(&self) -> syn::Result<TokenStream> {
@@ -80,34 +682,54 @@ impl<'a> StructInfo<'a> {
             ..
         } = *self;
         let (impl_generics, ty_generics, where_clause) = self.generics.split_for_impl();
-        let init_fields_type = type_tuple(self.included_fields().map(|f| {
-            if f.builder_attr.via_mutators.is_some() {
-                f.tuplized_type_ty_param()
-            } else {
-                empty_type()
-            }
-        }));
-        let builder_method_const = Rc::new(OnceCell::new());
-        let init_fields_expr = self
-            .included_fields()
-            .map({
-                let builder_method_const = builder_method_const.clone();
-                move |f| {
-                    f.builder_attr.via_mutators.as_ref().map_or_else(
-                        || quote!(()),
-                        |via_mutators| {
-                            let init = &via_mutators.init;
-                            if !matches!(init, syn::Expr::Lit(_)) {
-                                _ = builder_method_const.set(quote!());
-                            }
-                            quote!((#init,))
-                        },
+        let crate_module_path = &self.builder_attr.crate_module_path;
+        let init_fields_type = self.empty_state_type();
+        let (builder_method_args, arg_conversions, are_args_const) = self.builder_method_params()?;
+        // The builder method can only be `const` when it doesn't need to evaluate any expression.
+        let is_const = |preset: Option<&Preset>| {
+            are_args_const
+                && self.flattened_fields().next().is_none()
+                && self
+                    .included_fields()
+                    .filter_map(|f| f.builder_attr.via_mutators.as_ref().map(|via_mutators| &via_mutators.init))
+                    .chain(
+                        preset
+                            .into_iter()
+                            .flat_map(|preset| preset.fields.iter().map(|(_, value)| value)),
                     )
+                    .all(|expr| matches!(expr, syn::Expr::Lit(_)))
+        };
+        let const_qualifier = |preset: Option<&Preset>| is_const(preset).then(|| quote!(const));
+        let creation_body = |preset: Option<&Preset>| {
+            let init_fields_expr = self.included_fields().map(|f| {
+                if let Some(value) = preset.and_then(|preset| preset.value_of(f)) {
+                    quote!(#crate_module_path::state::Prefilled(#value))
+                } else if f.builder_attr.flatten.is_some() {
+                    let ty = f.ty;
+                    quote!(<#ty as #crate_module_path::HasBuilder>::builder())
+                } else if f.builder_attr.via_mutators.is_some() || self.is_builder_method_arg(f) {
+                    let name = &f.name;
+                    quote!(#crate_module_path::state::Set(#name))
+                } else {
+                    quote!(#crate_module_path::state::Unset)
                 }
-            })
-            .collect::<Box<[_]>>();
-        let builder_method_const = Rc::into_inner(builder_method_const).unwrap();
-        let builder_method_const = OnceCell::into_inner(builder_method_const).unwrap_or_else(|| quote!(const));
+            });
+            // The initial values of the fields set `via_mutators` can use the arguments of the
+            // builder method, so they are resolved before the arguments are moved into the builder.
+            let via_mutators_inits = self.included_fields().filter_map(|f| {
+                let name = &f.name;
+                let init = &f.builder_attr.via_mutators.as_ref()?.init;
+                Some(quote!(let #name = #init;))
+            });
+            quote! {
+                #arg_conversions
+                #( #via_mutators_inits )*
+                #builder_name {
+                    fields: (#(#init_fields_expr,)*),
+                    phantom: ::core::marker::PhantomData,
+                }
+            }
+        };
         let mut all_fields_param_type: syn::TypeParam =
             syn::Ident::new("TypedBuilderFields", proc_macro2::Span::call_site()).into();
         let all_fields_param = syn::GenericParam::Type(all_fields_param_type.clone());
@@ -120,59 +742,84 @@ impl<'a> StructInfo<'a> {
         let generics_with_empty = modify_types_generics_hack(&ty_generics, |args| {
             args.push(syn::GenericArgument::Type(init_fields_type.clone().into()));
         });
-        let phantom_generics = self.generics.params.iter().filter_map(|param| match param {
-            syn::GenericParam::Lifetime(lifetime) => {
-                let lifetime = &lifetime.lifetime;
-                Some(quote!(&#lifetime ()))
-            }
-            syn::GenericParam::Type(ty) => {
-                let ty = &ty.ident;
-                Some(ty.to_token_stream())
-            }
-            syn::GenericParam::Const(_cnst) => None,
-        });
+        let phantom_generics = self.phantom_generics();
 
-        let builder_method_name = self.builder_attr.builder_method.get_name().unwrap_or_else(|| quote!(builder));
+        let builder_method_name = self.builder_method_name();
         let builder_method_visibility = first_visibility(&[
-            self.builder_attr.builder_method.vis.as_ref(),
-            self.builder_attr.builder_type.vis.as_ref(),
+            self.builder_attr.builder_method.common.vis.as_ref(),
+            self.builder_attr.builder_type.common.vis.as_ref(),
             Some(vis),
         ]);
-        let builder_method_doc = self.builder_attr.builder_method.get_doc_or(|| {
-            format!(
-                "
-                Create a builder for building `{name}`.
-                On the builder, call {setters} to set the values of the fields.
-                Finally, call `.build()` to create the instance of `{name}`.
-                ",
-                name = self.name,
-                setters = {
-                    let mut result = String::new();
-                    let mut is_first = true;
-                    for field in self.setter_fields() {
-                        use std::fmt::Write;
-                        if is_first {
-                            is_first = false;
-                        } else {
-                            write!(&mut result, ", ").unwrap();
-                        }
-                        write!(&mut result, "`.{}(...)`", field.name).unwrap();
-                        if field.builder_attr.default.is_some() {
-                            write!(&mut result, "(optional)").unwrap();
-                        }
-                    }
-                    result
+        let setters = {
+            let mut result = String::new();
+            let mut is_first = true;
+            for field in self.setter_fields().filter(|f| !self.is_builder_method_arg(f)) {
+                use std::fmt::Write;
+                if is_first {
+                    is_first = false;
+                } else {
+                    write!(&mut result, ", ").unwrap();
                 }
-            )
-        });
-
-        let builder_type_visibility = first_visibility(&[self.builder_attr.builder_type.vis.as_ref(), Some(vis)]);
-        let builder_type_doc = if self.builder_attr.doc {
-            self.builder_attr.builder_type.get_doc_or(|| {
+                write!(&mut result, "`.{}(...)`", field.name).unwrap();
+                if field.builder_attr.default.is_some() {
+                    write!(&mut result, "(optional)").unwrap();
+                }
+            }
+            for setter in self.flattened_fields().flat_map(|field| &field.flattened_setters) {
+                use std::fmt::Write;
+                if is_first {
+                    is_first = false;
+                } else {
+                    write!(&mut result, ", ").unwrap();
+                }
+                write!(&mut result, "`.{}(...)`", setter.name).unwrap();
+            }
+            result
+        };
+        let builder_method_doc = match self.target {
+            BuilderTarget::Function { doc, .. } if self.builder_attr.builder_method.common.doc.is_none() && !doc.is_empty() => {
+                quote!(#(#doc)*)
+            }
+            BuilderTarget::Function { .. } => self.builder_attr.builder_method.common.get_doc_or(|| {
+                format!(
+                    "
+                    Create a builder for calling `{name}`.
+                    On the builder, call {setters} to set the values of the arguments.
+                    Finally, call `.{build_method_name}()` to call `{name}`.
+                    ",
+                    name = self.built_name(),
+                    build_method_name = self.build_method_name(),
+                )
+            }),
+            _ => self.builder_attr.builder_method.common.get_doc_or(|| {
                 format!(
-                    "Builder for [`{name}`] instances.\n\nSee [`{name}::builder()`] for more info.",
-                    name = name
+                    "
+                    Create a builder for building `{name}`.
+                    On the builder, call {setters} to set the values of the fields.
+                    Finally, call `.build()` to create the instance of `{name}`.
+                    ",
+                    name = self.built_name(),
                 )
+            }),
+        };
+
+        let builder_type_visibility = first_visibility(&[self.builder_attr.builder_type.common.vis.as_ref(), Some(vis)]);
+        let builder_type_doc = if self.builder_attr.doc {
+            self.builder_attr.builder_type.common.get_doc_or(|| {
+                if let BuilderTarget::Function { method, .. } = self.target {
+                    format!(
+                        "Builder for calling `{name}`.\n\nSee [`{prefix}{builder_method_name}()`] for more info.",
+                        name = self.built_name(),
+                        prefix = method.map_or_else(String::new, |method| format!("{}::", method.self_name)),
+                        builder_method_name = builder_method_name,
+                    )
+                } else {
+                    format!(
+                        "Builder for [`{built_name}`] instances.\n\nSee [`{name}::{builder_method_name}()`] for more info.",
+                        built_name = self.built_name(),
+                        builder_method_name = builder_method_name,
+                    )
+                }
             })
         } else {
             quote!(#[doc(hidden)])
@@ -186,18 +833,99 @@ impl<'a> StructInfo<'a> {
             b_generics_where.predicates.extend(predicates.predicates.clone());
         }
 
-        Ok(quote! {
-            #[automatically_derived]
-            impl #impl_generics #name #ty_generics #where_clause {
+        let builder_method_const = const_qualifier(None);
+        let builder_method_body = creation_body(None);
+        let builder_method = if let BuilderTarget::Function {
+            method: Some(method), ..
+        } = self.target
+        {
+            let MethodInfo {
+                impl_generics: method_impl_generics,
+                self_ty,
+                method_generics,
+                receiver,
+                ..
+            } = method;
+            let (method_impl_generics, _, method_impl_where_clause) = method_impl_generics.split_for_impl();
+            let (method_generics, _, method_where_clause) = method_generics.split_for_impl();
+            let receiver = receiver.as_ref().map(|receiver| quote!(self: #receiver));
+            let params = receiver.into_iter().chain(builder_method_args);
+            quote! {
+                #[automatically_derived]
+                impl #method_impl_generics #self_ty #method_impl_where_clause {
+                    #builder_method_doc
+                    #[allow(dead_code, clippy::default_trait_access, clippy::used_underscore_binding)]
+                    #builder_method_visibility #builder_method_const fn #builder_method_name #method_generics (#( #params ),*) -> #builder_name #generics_with_empty #method_where_clause {
+                        #builder_method_body
+                    }
+                }
+            }
+        } else if let BuilderTarget::Function { .. } = self.target {
+            quote! {
                 #builder_method_doc
-                #[allow(dead_code, clippy::default_trait_access)]
-                #builder_method_visibility #builder_method_const fn #builder_method_name() -> #builder_name #generics_with_empty {
-                    #builder_name {
-                        fields: (#(#init_fields_expr,)*),
-                        phantom: ::core::marker::PhantomData,
+                #[allow(dead_code, clippy::default_trait_access, clippy::used_underscore_binding)]
+                #builder_method_visibility #builder_method_const fn #builder_method_name #impl_generics (#( #builder_method_args ),*) -> #builder_name #generics_with_empty #where_clause {
+                    #builder_method_body
+                }
+            }
+        } else {
+            let preset_methods = self.builder_attr.presets.iter().map(|preset| {
+                let method_name = format_ident!("{}_builder", strip_raw_ident_prefix(preset.name.to_string()));
+                let fields = preset
+                    .fields
+                    .iter()
+                    .map(|(name, _)| format!("`{}`", strip_raw_ident_prefix(name.to_string())))
+                    .collect::<Vec<_>>();
+                let doc = format!(
+                    "Create a builder for building `{name}` with the `{preset}` preset, which sets {fields}. \
+                     Their setters replace the values of the preset.\n\nSee [`{name}::{builder_method_name}()`] for more info.",
+                    name = self.built_name(),
+                    preset = strip_raw_ident_prefix(preset.name.to_string()),
+                    fields = fields.join(", "),
+                );
+                let preset_const = const_qualifier(Some(preset));
+                let body = creation_body(Some(preset));
+                let generics_with_preset = modify_types_generics_hack(&ty_generics, |args| {
+                    args.push(syn::GenericArgument::Type(self.initial_state_type(Some(preset)).into()));
+                });
+                quote! {
+                    #[doc = #doc]
+                    #[allow(dead_code, clippy::default_trait_access, clippy::used_underscore_binding)]
+                    #builder_method_visibility #preset_const fn #method_name(#( #builder_method_args ),*) -> #builder_name #generics_with_preset {
+                        #body
+                    }
+                }
+            });
+            quote! {
+                #[automatically_derived]
+                impl #impl_generics #name #ty_generics #where_clause {
+                    #builder_method_doc
+                    #[allow(dead_code, clippy::default_trait_access, clippy::used_underscore_binding)]
+                    #builder_method_visibility #builder_method_const fn #builder_method_name(#( #builder_method_args ),*) -> #builder_name #generics_with_empty {
+                        #builder_method_body
+                    }
+
+                    #( #preset_methods )*
+                }
+            }
+        };
+
+        let has_builder_impl = self.has_builder_trait().then(|| {
+            quote! {
+                #[automatically_derived]
+                impl #impl_generics #crate_module_path::HasBuilder for #name #ty_generics #where_clause {
+                    type Builder = #builder_name #generics_with_empty;
+
+                    fn builder() -> Self::Builder {
+                        Self::#builder_method_name()
                     }
                 }
             }
+        });
+
+        Ok(quote! {
+            #builder_method
+            #has_builder_impl
 
             #[must_use]
             #builder_type_doc
@@ -220,51 +948,348 @@ impl<'a> StructInfo<'a> {
         })
     }
 
-    pub fn field_impl(&self, field: &FieldInfo<'_>) -> syn::Result<TokenStream> {
-        let StructInfo { ref builder_name, .. } = *self;
+    /// Methods for turning an instance of the struct back into a builder, with all the fields set
+    /// in a state that their setters can override.
+    pub fn prefilled_builder_impl(&self) -> TokenStream {
+        if self.builder_attr.into_builder.is_none() {
+            return quote!();
+        }
+        let StructInfo {
+            vis,
+            ref name,
+            ref builder_name,
+            ..
+        } = *self;
+        let crate_module_path = &self.builder_attr.crate_module_path;
+        let (impl_generics, ty_generics, where_clause) = self.generics.split_for_impl();
 
-        let descructuring = self.included_fields().map(|f| {
-            if f.ordinal == field.ordinal {
-                quote!(())
+        let prefilled_fields_type = type_tuple(self.included_fields().map(|f| {
+            let ty = f.ty;
+            if f.builder_attr.via_mutators.is_some() {
+                self.set_state_type(f)
+            } else if f.builder_attr.flatten.is_some() {
+                parse_quote!(<#ty as #crate_module_path::IntoBuilder>::Builder)
             } else {
-                let name = f.name;
-                name.to_token_stream()
+                parse_quote!(#crate_module_path::state::Prefilled<#ty>)
             }
+        }));
+        let generics_with_prefilled = modify_types_generics_hack(&ty_generics, |args| {
+            args.push(syn::GenericArgument::Type(prefilled_fields_type.clone().into()));
         });
-        let reconstructing = self.included_fields().map(|f| f.name);
-
-        let &FieldInfo {
-            name: field_name,
-            ty: field_type,
-            ..
-        } = field;
-        let mut ty_generics = self.generic_arguments();
-        let mut target_generics_tuple = empty_type_tuple();
-        let mut ty_generics_tuple = empty_type_tuple();
-        let generics = {
-            let mut generics = self.generics.clone();
-            for f in self.included_fields() {
-                if f.ordinal == field.ordinal {
-                    ty_generics_tuple.elems.push_value(empty_type());
-                    target_generics_tuple.elems.push_value(f.tuplized_type_ty_param());
-                } else {
-                    generics.params.push(f.generic_ty_param());
-                    let generic_argument: syn::Type = f.type_ident();
-                    ty_generics_tuple.elems.push_value(generic_argument.clone());
-                    target_generics_tuple.elems.push_value(generic_argument);
-                }
-                ty_generics_tuple.elems.push_punct(Default::default());
-                target_generics_tuple.elems.push_punct(Default::default());
+        let destructuring = self.included_fields().map(|f| {
+            let name = &f.name;
+            match &f.member {
+                syn::Member::Named(_) => quote!(#name),
+                member @ syn::Member::Unnamed(_) => quote!(#member: #name),
             }
-            generics
-        };
-        let mut target_generics = ty_generics.clone();
-        target_generics.push(syn::GenericArgument::Type(target_generics_tuple.into()));
-        ty_generics.push(syn::GenericArgument::Type(ty_generics_tuple.into()));
-        let (impl_generics, _, where_clause) = generics.split_for_impl();
-        let doc = field.builder_attr.setter.doc.as_ref().map(|doc| quote!(#[doc = #doc]));
-        let deprecated = &field.builder_attr.deprecated;
-
+        });
+        let rest = (self.included_fields().count() < self.fields.len()).then(|| quote!(..));
+        let prefilled_fields_expr = self.included_fields().map(|f| {
+            let name = &f.name;
+            if f.builder_attr.via_mutators.is_some() {
+                quote!(#crate_module_path::state::Set(#name))
+            } else if f.builder_attr.flatten.is_some() {
+                quote!(#crate_module_path::IntoBuilder::into_builder(#name))
+            } else {
+                quote!(#crate_module_path::state::Prefilled(#name))
+            }
+        });
+
+        let builder_method_visibility = first_visibility(&[
+            self.builder_attr.builder_method.common.vis.as_ref(),
+            self.builder_attr.builder_type.common.vis.as_ref(),
+            Some(vis),
+        ]);
+        let into_builder_doc = format!(
+            "
+            Create a builder with all the fields set to the values of this `{name}`.
+            Calling the setters of the fields replaces their values.
+            "
+        );
+        let to_builder_doc = format!(
+            "
+            Create a builder with all the fields set to clones of the values of this `{name}`.
+            Calling the setters of the fields replaces their values.
+            "
+        );
+
+        // The builder of a flattened field can only be named through `IntoBuilder`, which is not
+        // implemented for every struct. The bounds are made higher-ranked so that they are only
+        // checked when the methods are used.
+        let flattened_bounds = self
+            .flattened_fields()
+            .map(|f| {
+                let ty = f.ty;
+                quote!(for<'__typed_builder> #ty: #crate_module_path::IntoBuilder)
+            })
+            .collect::<Vec<_>>();
+        let into_builder_trait_impl = self.has_into_builder_trait().then(|| {
+            let mut generics = self.generics.clone();
+            generics.make_where_clause().predicates.extend(
+                flattened_bounds
+                    .iter()
+                    .map(|bound| -> syn::WherePredicate { parse_quote!(#bound) }),
+            );
+            let where_clause = &generics.where_clause;
+            quote! {
+                #[automatically_derived]
+                impl #impl_generics #crate_module_path::IntoBuilder for #name #ty_generics #where_clause {
+                    type Builder = #builder_name #generics_with_prefilled;
+
+                    fn into_builder(self) -> Self::Builder {
+                        Self::into_builder(self)
+                    }
+                }
+            }
+        });
+
+        quote! {
+            #[automatically_derived]
+            impl #impl_generics #name #ty_generics #where_clause {
+                #[doc = #into_builder_doc]
+                #[allow(dead_code)]
+                #builder_method_visibility fn into_builder(self) -> #builder_name #generics_with_prefilled
+                where
+                    #( #flattened_bounds, )*
+                {
+                    #[allow(deprecated)]
+                    let #name { #( #destructuring, )* #rest } = self;
+                    #builder_name {
+                        fields: ( #( #prefilled_fields_expr, )* ),
+                        phantom: ::core::marker::PhantomData,
+                    }
+                }
+
+                #[doc = #to_builder_doc]
+                #[allow(dead_code)]
+                // The bound is made higher-ranked so that it is only checked when the method is used,
+                // rather than being rejected for types that don't implement `Clone`.
+                #builder_method_visibility fn to_builder(&self) -> #builder_name #generics_with_prefilled
+                where
+                    for<'__typed_builder> Self: ::core::clone::Clone,
+                    #( #flattened_bounds, )*
+                {
+                    Self::into_builder(::core::clone::Clone::clone(self))
+                }
+            }
+
+            #into_builder_trait_impl
+        }
+    }
+
+    pub fn field_impl(&self, field: &FieldInfo<'_>) -> syn::Result<TokenStream> {
+        if let Some(each) = &field.builder_attr.setter.each {
+            return Ok(self.each_field_impl(field, each));
+        }
+        let StructInfo { ref builder_name, .. } = *self;
+
+        let field_name = &field.name;
+        let field_type = field.ty;
+        let builder_module_name = self.builder_module_name();
+        let state_trait = field.state_trait_name();
+        let can_set_trait = field.can_set_trait_name();
+        let can_set_trait = quote!(#builder_module_name::#can_set_trait);
+        let (mut generics, ty_generics) = self.any_state_generics();
+        let states = match self.ordered_setter_states(field) {
+            Some(states) => states.into_iter().map(|(generics, state)| (generics, state.into())).collect(),
+            None => vec![(generics.clone(), parse_quote!(TypedBuilderFields))],
+        };
+        generics
+            .make_where_clause()
+            .predicates
+            .extend(self.ordered_setter_bounds(field, &parse_quote!(TypedBuilderFields)));
+        let mut target_generics = self.generic_arguments();
+        target_generics.push(parse_quote!(<TypedBuilderFields as #builder_module_name::#state_trait>::WithValue<#field_type>));
+        let doc = field.builder_attr.setter.doc.as_ref().map(|doc| quote!(#[doc = #doc]));
+        let deprecated = &field.builder_attr.deprecated;
+
+        let SetterArgs {
+            params: param_list,
+            value: arg_expr,
+            generics: method_generics,
+            bounds: nested_bounds,
+            flatten: flatten_args,
+        } = self.setter_args(field)?;
+        let flatten_args = flatten_args.filter(|_| self.is_flattenable());
+
+        let method_name = field.setter_method_name();
+        let flatten_setter_impl = flatten_args.map(|(arg_types, arg_generics)| {
+            self.flatten_setter_impl(
+                self.flatten_index(field),
+                &generics,
+                arg_generics,
+                quote!(TypedBuilderFields: #can_set_trait),
+                &arg_types,
+                quote!(#builder_name <#ty_generics>),
+                quote!(#builder_name <#target_generics>),
+                &method_name,
+            )
+        });
+
+        let setter_impls = states
+            .into_iter()
+            .map(|(mut state_generics, state): (syn::Generics, syn::Type)| {
+                // Fixing the state of the previous field is not enough when the builder was created with
+                // later fields already set - by the builder method or a preset.
+                state_generics
+                    .make_where_clause()
+                    .predicates
+                    .extend(self.ordered_setter_bounds(field, &state));
+                let (impl_generics, _, where_clause) = state_generics.split_for_impl();
+                let mut ty_generics = self.generic_arguments();
+                ty_generics.push(syn::GenericArgument::Type(state.clone()));
+                let mut target_generics = self.generic_arguments();
+                target_generics.push(parse_quote!(<#state as #builder_module_name::#state_trait>::WithValue<#field_type>));
+                quote! {
+                    #[allow(dead_code, non_camel_case_types, missing_docs)]
+                    #[automatically_derived]
+                    impl #impl_generics #builder_name <#ty_generics> #where_clause {
+                        #deprecated
+                        #doc
+                        #[allow(clippy::used_underscore_binding)]
+                        pub fn #method_name #method_generics (self, #param_list) -> #builder_name <#target_generics>
+                        where
+                            #state: #can_set_trait,
+                            #nested_bounds
+                        {
+                            let #field_name = #arg_expr;
+                            #builder_name {
+                                fields: #builder_module_name::#state_trait::set(self.fields, #field_name),
+                                phantom: self.phantom,
+                            }
+                        }
+                    }
+                }
+            });
+
+        Ok(quote! {
+            #( #setter_impls )*
+
+            #flatten_setter_impl
+        })
+    }
+
+    /// The visibility of the methods that give the value of a field back - which must not make it
+    /// more visible than the field itself. The fields of variants and functions have no visibility
+    /// of their own, so they are as visible as the builder.
+    fn field_value_visibility(&self, field: &FieldInfo<'_>) -> TokenStream {
+        match self.target {
+            BuilderTarget::Struct => field.vis.to_token_stream(),
+            BuilderTarget::Variant(_) | BuilderTarget::Function { .. } => {
+                first_visibility(&[self.builder_attr.builder_type.common.vis.as_ref(), Some(self.vis)])
+            }
+        }
+    }
+
+    /// Methods that move a set field back out of the builder, so that it can be set again or left to
+    /// its default.
+    pub fn unset_field_impl(&self, field: &FieldInfo<'_>) -> TokenStream {
+        let StructInfo { ref builder_name, .. } = *self;
+        let FieldInfo {
+            name: field_name,
+            ty: field_type,
+            ..
+        } = field;
+
+        let builder_module_name = self.builder_module_name();
+        let state_trait = field.state_trait_name();
+        let is_set_trait = field.is_set_trait_name();
+        let is_set_trait = quote!(#builder_module_name::#is_set_trait);
+        let (mut generics, ty_generics) = self.any_state_generics();
+        generics
+            .make_where_clause()
+            .predicates
+            .extend(self.ordered_unset_bounds(field, &parse_quote!(TypedBuilderFields)));
+        let mut target_generics = self.generic_arguments();
+        target_generics.push(parse_quote!(<TypedBuilderFields as #builder_module_name::#state_trait>::WithoutValue));
+        let (impl_generics, _, where_clause) = generics.split_for_impl();
+        let deprecated = &field.builder_attr.deprecated;
+
+        let field_name_str = strip_raw_ident_prefix(field_name.to_string());
+        let take_method_name = format_ident!("take_{}", field_name_str);
+        let unset_method_name = format_ident!("unset_{}", field_name_str);
+        let take_doc = format!("Take the value of `{field_name_str}` out of the builder, leaving it unset.");
+        let unset_doc = format!("Unset `{field_name_str}`, dropping its value.");
+        let visibility = self.field_value_visibility(field);
+
+        quote! {
+            #[allow(dead_code, non_camel_case_types, missing_docs)]
+            #[automatically_derived]
+            impl #impl_generics #builder_name <#ty_generics> #where_clause {
+                #deprecated
+                #[doc = #take_doc]
+                #visibility fn #take_method_name (self) -> (#field_type, #builder_name <#target_generics>)
+                where
+                    TypedBuilderFields: #is_set_trait<Value = #field_type>,
+                {
+                    let (#field_name, fields) = #is_set_trait::take(self.fields);
+                    (
+                        #field_name,
+                        #builder_name {
+                            fields,
+                            phantom: self.phantom,
+                        },
+                    )
+                }
+
+                #deprecated
+                #[doc = #unset_doc]
+                #visibility fn #unset_method_name (self) -> #builder_name <#target_generics>
+                where
+                    TypedBuilderFields: #is_set_trait<Value = #field_type>,
+                {
+                    self.#take_method_name().1
+                }
+            }
+        }
+    }
+
+    /// A getter for the field, which is available once the field is set.
+    pub fn getter_impl(&self, field: &FieldInfo<'_>) -> TokenStream {
+        let StructInfo { ref builder_name, .. } = *self;
+        let field_type = field.ty;
+
+        let builder_module_name = self.builder_module_name();
+        let is_set_trait = field.is_set_trait_name();
+        let is_set_trait = quote!(#builder_module_name::#is_set_trait);
+        let (generics, ty_generics) = self.any_state_generics();
+        let (impl_generics, _, where_clause) = generics.split_for_impl();
+
+        let getter_method_name = field.getter_method_name();
+        let doc = field
+            .builder_attr
+            .setter
+            .getter
+            .as_ref()
+            .and_then(|getter| getter.doc.as_ref())
+            .map(|doc| quote!(#[doc = #doc]));
+        let deprecated = &field.builder_attr.deprecated;
+        let visibility = self.field_value_visibility(field);
+
+        quote! {
+            #[allow(dead_code, non_camel_case_types, missing_docs)]
+            #[automatically_derived]
+            impl #impl_generics #builder_name <#ty_generics> #where_clause {
+                #deprecated
+                #doc
+                #visibility fn #getter_method_name (&self) -> &#field_type
+                where
+                    TypedBuilderFields: #is_set_trait<Value = #field_type>,
+                {
+                    #is_set_trait::value(&self.fields)
+                }
+            }
+        }
+    }
+
+    /// The parameters of the setter of a field, and how the value of the field is made from them.
+    fn setter_args(&self, field: &FieldInfo<'_>) -> syn::Result<SetterArgs> {
+        let FieldInfo {
+            name: field_name,
+            ty: field_type,
+            ..
+        } = field;
+
         // NOTE: both auto_into and strip_option affect `arg_type` and `arg_expr`, but the order of
         // nesting is different so we have to do this little dance.
         let arg_type = if field.builder_attr.setter.strip_option.is_some() && field.builder_attr.setter.transform.is_none() {
@@ -274,14 +1299,47 @@ impl<'a> StructInfo<'a> {
         } else {
             field_type
         };
+        // The arguments of the setter for the `FlattenSetter` implementation, with generic
+        // parameters instead of `impl Trait`.
+        let flatten = if field.builder_attr.setter.nested.is_some() {
+            None
+        } else if field.builder_attr.setter.strip_bool.is_some() {
+            Some((Vec::new(), Vec::new()))
+        } else if let Some(transform) = &field.builder_attr.setter.transform {
+            Some((
+                transform.params.iter().map(|(_, ty)| ty.to_token_stream()).collect(),
+                Vec::new(),
+            ))
+        } else if field.builder_attr.setter.auto_into.is_some() {
+            Some((vec![quote!(__A0)], vec![quote!(__A0: ::core::convert::Into<#arg_type>)]))
+        } else {
+            Some((vec![arg_type.to_token_stream()], Vec::new()))
+        };
         let (arg_type, arg_expr) = if field.builder_attr.setter.auto_into.is_some() {
             (quote!(impl ::core::convert::Into<#arg_type>), quote!(#field_name.into()))
         } else {
             (arg_type.to_token_stream(), field_name.to_token_stream())
         };
 
-        let (param_list, arg_expr) = if field.builder_attr.setter.strip_bool.is_some() {
+        let crate_module_path = &self.builder_attr.crate_module_path;
+        let mut bounds = None;
+        let (params, value) = if field.builder_attr.setter.strip_bool.is_some() {
             (quote!(), quote!(true))
+        } else if field.builder_attr.setter.nested.is_some() {
+            // The closure can only return a builder with all the required fields set, because that's
+            // what implements `Build`.
+            bounds = Some(quote!(__B: #crate_module_path::Build<Output = #arg_type>,));
+            let built = quote! {
+                #crate_module_path::Build::build(#field_name(<#arg_type as #crate_module_path::HasBuilder>::builder()))
+            };
+            (
+                quote!(#field_name: impl ::core::ops::FnOnce(<#arg_type as #crate_module_path::HasBuilder>::Builder) -> __B),
+                if field.builder_attr.setter.strip_option.is_some() {
+                    quote!(Some(#built))
+                } else {
+                    built
+                },
+            )
         } else if let Some(transform) = &field.builder_attr.setter.transform {
             let params = transform.params.iter().map(|(pat, ty)| quote!(#pat: #ty));
             let body = &transform.body;
@@ -292,137 +1350,682 @@ impl<'a> StructInfo<'a> {
             (quote!(#field_name: #arg_type), arg_expr)
         };
 
-        let repeated_fields_error_type_name = syn::Ident::new(
-            &format!(
-                "{}_Error_Repeated_field_{}",
-                builder_name,
-                strip_raw_ident_prefix(field_name.to_string())
-            ),
-            proc_macro2::Span::call_site(),
+        Ok(SetterArgs {
+            params,
+            value,
+            generics: bounds.is_some().then(|| quote!(<__B>)),
+            bounds,
+            flatten,
+        })
+    }
+
+    /// Implement `FlattenSetter` for a setter, so that the builders of structs that flatten this
+    /// one can call it.
+    #[allow(clippy::too_many_arguments)]
+    fn flatten_setter_impl(
+        &self,
+        index: usize,
+        generics: &syn::Generics,
+        arg_generics: Vec<TokenStream>,
+        bounds: TokenStream,
+        arg_types: &[TokenStream],
+        self_type: TokenStream,
+        output_type: TokenStream,
+        method_name: &Ident,
+    ) -> TokenStream {
+        let crate_module_path = &self.builder_attr.crate_module_path;
+        let mut generics = generics.clone();
+        generics.params.extend(
+            arg_generics
+                .into_iter()
+                .map(|param| -> syn::GenericParam { parse_quote!(#param) }),
         );
-        let repeated_fields_error_message = format!("Repeated field {}", field_name);
+        generics.make_where_clause().predicates.push(parse_quote!(#bounds));
+        let (impl_generics, _, where_clause) = generics.split_for_impl();
+        let arg_names = (0..arg_types.len()).map(|i| format_ident!("__{}", i)).collect::<Vec<_>>();
+        quote! {
+            #[allow(dead_code, non_camel_case_types, missing_docs)]
+            #[automatically_derived]
+            impl #impl_generics #crate_module_path::FlattenSetter<#index, (#( #arg_types, )*)> for #self_type #where_clause {
+                type Output = #output_type;
+
+                #[allow(deprecated)]
+                fn set(self, (#( #arg_names, )*): (#( #arg_types, )*)) -> Self::Output {
+                    Self::#method_name(self, #( #arg_names ),*)
+                }
+            }
+        }
+    }
+
+    /// The parameter types of the setters of a collection field - first for a single item and then
+    /// for the field's own setter - and the expressions of the items they add.
+    fn each_setter_args(field: &FieldInfo<'_>, each: &Ident) -> (TokenStream, TokenStream, TokenStream, TokenStream) {
+        let FieldInfo {
+            name: field_name,
+            ty: field_type,
+            ..
+        } = field;
+        let item_type = quote!(<#field_type as ::core::iter::IntoIterator>::Item);
+        if field.builder_attr.setter.auto_into.is_some() {
+            (
+                quote!(impl ::core::convert::Into<#item_type>),
+                quote!(impl ::core::iter::IntoIterator<Item = impl ::core::convert::Into<#item_type>>),
+                quote!(::core::convert::Into::into(#each)),
+                quote!(::core::iter::Iterator::map(::core::iter::IntoIterator::into_iter(#field_name), ::core::convert::Into::into)),
+            )
+        } else {
+            (
+                item_type.clone(),
+                quote!(impl ::core::iter::IntoIterator<Item = #item_type>),
+                each.to_token_stream(),
+                field_name.to_token_stream(),
+            )
+        }
+    }
 
+    /// Setters for a collection field, which can be called any number of times to add items to it.
+    fn each_field_impl(&self, field: &FieldInfo<'_>, each: &Ident) -> TokenStream {
+        let StructInfo { ref builder_name, .. } = *self;
+        let FieldInfo {
+            name: field_name,
+            ty: field_type,
+            ..
+        } = field;
+        let crate_module_path = &self.builder_attr.crate_module_path;
+
+        let any_state = || {
+            let mut generics = self.generics.clone();
+            generics
+                .params
+                .extend(self.included_fields().map(FieldInfo::generic_ty_param));
+            (generics, type_tuple(self.included_fields().map(FieldInfo::type_ident)))
+        };
+        // The collection can be extended in any state of the field.
+        let field_generic = &field.generic_ident;
+        let with_field_state = |(mut generics, state): (syn::Generics, syn::TypeTuple)| {
+            generics
+                .make_where_clause()
+                .predicates
+                .push(parse_quote!(#field_generic: #crate_module_path::state::Optional<#field_type>));
+            let target_state = type_tuple(self.included_fields().zip(&state.elems).map(|(f, state)| {
+                if f.ordinal == field.ordinal {
+                    self.set_state_type(f)
+                } else {
+                    state.clone()
+                }
+            }));
+            let mut ty_generics = self.generic_arguments();
+            ty_generics.push(syn::GenericArgument::Type(state.into()));
+            let mut target_generics = self.generic_arguments();
+            target_generics.push(syn::GenericArgument::Type(target_state.into()));
+            (generics, ty_generics, target_generics)
+        };
+        let states = self
+            .ordered_setter_states(field)
+            .unwrap_or_else(|| vec![any_state()])
+            .into_iter()
+            .map(|(mut generics, state)| {
+                generics
+                    .make_where_clause()
+                    .predicates
+                    .extend(self.ordered_setter_bounds(field, &state.clone().into()));
+                with_field_state((generics, state))
+            })
+            .collect::<Vec<_>>();
+        // The trait implementations for flattening can bound the state instead.
+        let (mut generics, ty_generics, target_generics) = with_field_state(any_state());
+        let ordered_bounds =
+            self.ordered_setter_bounds(field, &type_tuple(self.included_fields().map(FieldInfo::type_ident)).into());
+        generics.make_where_clause().predicates.extend(ordered_bounds);
+        let doc = field.builder_attr.setter.doc.as_ref().map(|doc| quote!(#[doc = #doc]));
+        let deprecated = &field.builder_attr.deprecated;
+
+        let item_type = quote!(<#field_type as ::core::iter::IntoIterator>::Item);
+        let (item_arg_type, items_arg_type, item_expr, items_expr) = Self::each_setter_args(field, each);
         let method_name = field.setter_method_name();
 
-        Ok(quote! {
-            #[allow(dead_code, non_camel_case_types, missing_docs)]
-            #[automatically_derived]
-            impl #impl_generics #builder_name <#ty_generics> #where_clause {
-                #deprecated
-                #doc
-                #[allow(clippy::used_underscore_binding)]
-                pub fn #method_name (self, #param_list) -> #builder_name <#target_generics> {
-                    let #field_name = (#arg_expr,);
-                    let ( #(#descructuring,)* ) = self.fields;
-                    #builder_name {
-                        fields: ( #(#reconstructing,)* ),
-                        phantom: self.phantom,
-                    }
+        let extend = |items: TokenStream| {
+            let descructuring = self.included_fields().map(|f| &f.name);
+            let reconstructing = self.included_fields().map(|f| {
+                let name = &f.name;
+                if f.ordinal == field.ordinal {
+                    quote!(#crate_module_path::state::Set(#name))
+                } else {
+                    name.to_token_stream()
+                }
+            });
+            quote! {
+                let __items = #items;
+                let ( #(#descructuring,)* ) = self.fields;
+                let mut #field_name = #crate_module_path::state::Optional::into_value(#field_name, ::core::default::Default::default);
+                ::core::iter::Extend::extend(&mut #field_name, __items);
+                #builder_name {
+                    fields: ( #(#reconstructing,)* ),
+                    phantom: self.phantom,
                 }
             }
-            #[doc(hidden)]
-            #[allow(dead_code, non_camel_case_types, non_snake_case)]
-            #[allow(clippy::exhaustive_enums)]
-            pub enum #repeated_fields_error_type_name {}
-            #[doc(hidden)]
-            #[allow(dead_code, non_camel_case_types, missing_docs)]
-            #[automatically_derived]
-            impl #impl_generics #builder_name <#target_generics> #where_clause {
-                #[deprecated(
-                    note = #repeated_fields_error_message
-                )]
-                pub fn #method_name (self, _: #repeated_fields_error_type_name) -> #builder_name <#target_generics> {
-                    self
+        };
+        let collection_bounds = quote! {
+            where
+                #field_type: ::core::iter::Extend<#item_type> + ::core::default::Default,
+        };
+        let add_item = extend(quote!(::core::iter::once(#item_expr)));
+        let add_items = extend(items_expr);
+
+        let flatten_setter_impls = self.is_flattenable().then(|| {
+            let index = self.flatten_index(field);
+            let self_type = quote!(#builder_name <#ty_generics>);
+            let output_type = quote!(#builder_name <#target_generics>);
+            let collection_bound = quote!(#field_type: ::core::iter::Extend<#item_type> + ::core::default::Default);
+            let (items_generics, item_generics, item_arg_type) = if field.builder_attr.setter.auto_into.is_some() {
+                (
+                    vec![
+                        quote!(__A0: ::core::iter::IntoIterator<Item = __A1>),
+                        quote!(__A1: ::core::convert::Into<#item_type>),
+                    ],
+                    vec![quote!(__A0: ::core::convert::Into<#item_type>)],
+                    quote!(__A0),
+                )
+            } else {
+                (
+                    vec![quote!(__A0: ::core::iter::IntoIterator<Item = #item_type>)],
+                    Vec::new(),
+                    item_type.clone(),
+                )
+            };
+            let items_impl = self.flatten_setter_impl(
+                index,
+                &generics,
+                items_generics,
+                collection_bound.clone(),
+                &[quote!(__A0)],
+                self_type.clone(),
+                output_type.clone(),
+                &method_name,
+            );
+            let item_impl = self.flatten_setter_impl(
+                index + 1,
+                &generics,
+                item_generics,
+                collection_bound,
+                &[item_arg_type],
+                self_type,
+                output_type,
+                each,
+            );
+            quote!(#items_impl #item_impl)
+        });
+
+        let setter_impls = states.iter().map(|(generics, ty_generics, target_generics)| {
+            let (impl_generics, _, where_clause) = generics.split_for_impl();
+            quote! {
+                #[allow(dead_code, non_camel_case_types, missing_docs)]
+                #[automatically_derived]
+                impl #impl_generics #builder_name <#ty_generics> #where_clause {
+                    #deprecated
+                    #doc
+                    #[allow(clippy::used_underscore_binding)]
+                    pub fn #each (self, #each: #item_arg_type) -> #builder_name <#target_generics> #collection_bounds {
+                        #add_item
+                    }
+
+                    #deprecated
+                    #doc
+                    #[allow(clippy::used_underscore_binding)]
+                    pub fn #method_name (self, #field_name: #items_arg_type) -> #builder_name <#target_generics> #collection_bounds {
+                        #add_items
+                    }
                 }
             }
-        })
+        });
+
+        quote! {
+            #( #setter_impls )*
+
+            #flatten_setter_impls
+        }
     }
 
-    pub fn required_field_impl(&self, field: &FieldInfo<'_>) -> TokenStream {
-        let StructInfo { builder_name, .. } = self;
+    /// The setters of the builder of a flattened field's type, which set the field through the
+    /// builder it holds.
+    pub fn flattened_field_impl(&self, field: &FieldInfo<'_>) -> TokenStream {
+        let StructInfo { ref builder_name, .. } = *self;
+        let crate_module_path = &self.builder_attr.crate_module_path;
+        let FieldInfo {
+            name: field_name,
+            generic_ident: field_generic,
+            ..
+        } = field;
 
-        let FieldInfo { name: field_name, .. } = *field;
-        let mut builder_generics: Vec<syn::GenericArgument> = self
-            .generics
-            .params
+        let mut generics = self.generics.clone();
+        let mut ty_generics = self.generic_arguments();
+        let mut ty_generics_tuple = empty_type_tuple();
+        for f in self.included_fields() {
+            generics.params.push(f.generic_ty_param());
+            ty_generics_tuple.elems.push(f.type_ident());
+        }
+        ty_generics.push(syn::GenericArgument::Type(ty_generics_tuple.into()));
+        let (impl_generics, _, where_clause) = generics.split_for_impl();
+        let deprecated = &field.builder_attr.deprecated;
+        let descructuring = self.included_fields().map(|f| &f.name).collect::<Vec<_>>();
+        let first_index = self.flatten_index(field);
+
+        field
+            .flattened_setters
             .iter()
-            .map(|generic_param| match generic_param {
-                syn::GenericParam::Type(type_param) => {
-                    let ident = type_param.ident.to_token_stream();
-                    syn::parse2(ident).unwrap()
+            .enumerate()
+            .filter_map(|(index, setter)| {
+                let arg_generics = (0..setter.arity?).map(|i| format_ident!("__A{}", i)).collect::<Vec<_>>();
+                let arg_names = (0..arg_generics.len()).map(|i| format_ident!("__{}", i)).collect::<Vec<_>>();
+                let setter_trait = quote!(#crate_module_path::FlattenSetter<#index, (#( #arg_generics, )*)>);
+                let mut target_generics = self.generic_arguments();
+                target_generics.push(syn::GenericArgument::Type(
+                    type_tuple(self.included_fields().map(|f| {
+                        if f.ordinal == field.ordinal {
+                            parse_quote!(<#field_generic as #setter_trait>::Output)
+                        } else {
+                            f.type_ident()
+                        }
+                    }))
+                    .into(),
+                ));
+                let method_name = &setter.name;
+                let forwarding_impl = self.is_flattenable().then(|| {
+                    self.flatten_setter_impl(
+                        first_index + index,
+                        &generics,
+                        arg_generics.iter().map(ToTokens::to_token_stream).collect(),
+                        quote!(#field_generic: #setter_trait),
+                        &arg_generics.iter().map(ToTokens::to_token_stream).collect::<Vec<_>>(),
+                        quote!(#builder_name <#ty_generics>),
+                        quote!(#builder_name <#target_generics>),
+                        method_name,
+                    )
+                });
+                Some(quote! {
+                    #[allow(dead_code, non_camel_case_types, missing_docs)]
+                    #[automatically_derived]
+                    impl #impl_generics #builder_name <#ty_generics> #where_clause {
+                        #deprecated
+                        #[allow(clippy::used_underscore_binding)]
+                        pub fn #method_name <#( #arg_generics ),*> (self, #( #arg_names: #arg_generics ),*) -> #builder_name <#target_generics>
+                        where
+                            #field_generic: #setter_trait,
+                        {
+                            let ( #(#descructuring,)* ) = self.fields;
+                            let #field_name = <#field_generic as #setter_trait>::set(#field_name, (#( #arg_names, )*));
+                            #builder_name {
+                                fields: ( #(#descructuring,)* ),
+                                phantom: self.phantom,
+                            }
+                        }
+                    }
+
+                    #forwarding_impl
+                })
+            })
+            .collect()
+    }
+
+    /// A macro named after the struct, which lets the derives of structs that flatten it get the
+    /// setters of its builder.
+    pub fn flatten_macro_impl(&self) -> TokenStream {
+        if !self.is_flattenable() {
+            return quote!();
+        }
+        let name = self.name;
+        let crate_module_path = &self.builder_attr.crate_module_path;
+        let macro_name = format_ident!("__typed_builder_flatten_{}", strip_raw_ident_prefix(name.to_string()));
+        let setters = self.flattened_setters();
+        quote! {
+            #[doc(hidden)]
+            #[allow(unused_macros)]
+            macro_rules! #macro_name {
+                ($($input:tt)*) => {
+                    #crate_module_path::__flatten_builder! { $($input)* { #( #setters )* } }
+                };
+            }
+
+            #[doc(hidden)]
+            #[allow(unused_imports)]
+            pub(crate) use #macro_name as #name;
+        }
+    }
+
+    /// The module that holds the items the builder needs for its bounds, named after the builder.
+    pub fn builder_module_name(&self) -> Ident {
+        if let Some(module) = &self.builder_attr.builder_type.module {
+            return module.clone();
+        }
+        Ident::new(
+            &to_snake_case(&strip_raw_ident_prefix(self.builder_name.to_string())),
+            self.builder_name.span(),
+        )
+    }
+
+    /// Fields that must be set before the builder can be built.
+    pub fn required_fields(&self) -> impl DoubleEndedIterator<Item = &FieldInfo<'a>> {
+        self.setter_fields().filter(|f| f.builder_attr.default.is_none())
+    }
+
+    pub fn builder_module_impl(&self) -> TokenStream {
+        let builder_name = &self.builder_name;
+        let builder_module_name = self.builder_module_name();
+        let builder_module_visibility = first_visibility(&[self.builder_attr.builder_type.common.vis.as_ref(), Some(self.vis)]);
+
+        // Each required field gets a type named after it, which the build method's bound uses to
+        // list the fields that are not set yet. The type only exists for that list, so it has no
+        // values.
+        let crate_module_path = &self.builder_attr.crate_module_path;
+        let missing_field_markers = self.required_fields().map(|field| {
+            let name = &field.name;
+            quote_spanned! {name.span() =>
+                #[doc(hidden)]
+                pub enum #name {}
+
+                impl #crate_module_path::MissingFieldList for #name {
+                    type With<Missing: #crate_module_path::MissingFieldList> = (Missing, #name);
                 }
-                syn::GenericParam::Lifetime(lifetime_def) => syn::GenericArgument::Lifetime(lifetime_def.lifetime.clone()),
-                syn::GenericParam::Const(const_param) => {
-                    let ident = const_param.ident.to_token_stream();
-                    syn::parse2(ident).unwrap()
+            }
+        });
+        // The state of the builder with the field in the given state, and the generic parameters
+        // for the states of the other fields.
+        let state_with = |field: &FieldInfo<'_>, field_state: syn::Type| {
+            let params = self
+                .included_fields()
+                .filter(|f| f.ordinal != field.ordinal)
+                .map(|f| f.generic_ident.clone())
+                .collect::<Vec<_>>();
+            let state = type_tuple(self.included_fields().map(|f| {
+                if f.ordinal == field.ordinal {
+                    field_state.clone()
+                } else {
+                    f.type_ident()
                 }
-            })
-            .collect();
-        let mut builder_generics_tuple = empty_type_tuple();
-        let generics = {
-            let mut generics = self.generics.clone();
-            for f in self.included_fields() {
-                if f.builder_attr.default.is_some() || f.builder_attr.via_mutators.is_some() {
-                    // `f` is not mandatory - it does not have it's own fake `build` method, so `field` will need
-                    // to warn about missing `field` whether or not `f` is set.
-                    assert!(
-                        f.ordinal != field.ordinal,
-                        "`required_field_impl` called for optional field {}",
-                        field.name
-                    );
-                    generics.params.push(f.generic_ty_param());
-                    builder_generics_tuple.elems.push_value(f.type_ident());
-                } else if f.ordinal < field.ordinal {
-                    // Only add a `build` method that warns about missing `field` if `f` is set. If `f` is not set,
-                    // `f`'s `build` method will warn, since it appears earlier in the argument list.
-                    builder_generics_tuple.elems.push_value(f.tuplized_type_ty_param());
-                } else if f.ordinal == field.ordinal {
-                    builder_generics_tuple.elems.push_value(empty_type());
+            }));
+            (params, state)
+        };
+        let state_fields = |field: &FieldInfo<'_>, field_expr: TokenStream| {
+            let names = self.included_fields().map(|f| {
+                if f.ordinal == field.ordinal {
+                    quote!(_)
                 } else {
-                    // `f` appears later in the argument list after `field`, so if they are both missing we will
-                    // show a warning for `field` and not for `f` - which means this warning should appear whether
-                    // or not `f` is set.
-                    generics.params.push(f.generic_ty_param());
-                    builder_generics_tuple.elems.push_value(f.type_ident());
+                    f.name.to_token_stream()
                 }
-
-                builder_generics_tuple.elems.push_punct(Default::default());
-            }
-            generics
+            });
+            let values = self.included_fields().map(|f| {
+                if f.ordinal == field.ordinal {
+                    field_expr.clone()
+                } else {
+                    f.name.to_token_stream()
+                }
+            });
+            (quote!(( #( #names, )* )), quote!(( #( #values, )* )))
         };
 
-        builder_generics.push(syn::GenericArgument::Type(builder_generics_tuple.into()));
-        let (impl_generics, _, where_clause) = generics.split_for_impl();
+        // Each field has a trait for changing its state, which is implemented for every state of
+        // the builder so that the types of the methods that change it can always be resolved, and
+        // traits for the states it needs to be in for calling these methods. Keeping them apart
+        // makes calling a method in the wrong state fail on the latter, with its proper error.
+        let field_traits = self
+            .included_fields()
+            .filter(|field| field.builder_attr.flatten.is_none())
+            .map(|field| {
+                let state_trait = field.state_trait_name();
+                let is_set_trait = field.is_set_trait_name();
+                let field_name = strip_raw_ident_prefix(field.name.to_string());
+                let state_doc =
+                    format!("Implemented for all the states of [`{builder_name}`], for changing the state of `{field_name}`.");
+                let is_set_doc = format!("Implemented for the states of [`{builder_name}`] in which `{field_name}` is set.");
+                let is_set_message = format!("field `{field_name}` is not set on `{builder_name}`");
+                let can_set_trait =
+                    (field.builder_attr.via_mutators.is_none() && field.builder_attr.setter.each.is_none()).then(|| {
+                        let can_set_trait = field.can_set_trait_name();
+                        let doc = format!("Implemented for the states of [`{builder_name}`] in which `{field_name}` can be set.");
+                        let message = format!("field `{field_name}` was already set on `{builder_name}`");
+                        let label = format!("`{field_name}` is already set");
+                        quote! {
+                            #[doc = #doc]
+                            #[diagnostic::on_unimplemented(message = #message, label = #label)]
+                            pub trait #can_set_trait: #state_trait {}
+                        }
+                    });
+                let is_unset_trait = field.is_unset_trait_name();
+                let is_unset_doc =
+                    format!("Implemented for the states of [`{builder_name}`] in which `{field_name}` is not set.");
+                let is_unset_message = format!("field `{field_name}` is set on `{builder_name}`");
+                quote! {
+                    #[doc = #state_doc]
+                    pub trait #state_trait {
+                        /// The state of the builder with the field set to a value.
+                        type WithValue<TypedBuilderValue>;
+                        /// The state of the builder with the field unset.
+                        type WithoutValue;
+
+                        /// Set the field to a value.
+                        fn set<TypedBuilderValue>(self, value: TypedBuilderValue) -> Self::WithValue<TypedBuilderValue>;
+                    }
+
+                    #can_set_trait
+
+                    #[doc = #is_unset_doc]
+                    #[diagnostic::on_unimplemented(message = #is_unset_message)]
+                    pub trait #is_unset_trait: #state_trait {}
+
+                    #[doc = #is_set_doc]
+                    #[diagnostic::on_unimplemented(
+                        message = #is_set_message,
+                        label = "only fields that are set can be read or taken out of the builder",
+                    )]
+                    pub trait #is_set_trait: #state_trait {
+                        /// The type of the field.
+                        type Value;
+
+                        /// A reference to the value of the field.
+                        fn value(&self) -> &Self::Value;
 
-        let early_build_error_type_name = syn::Ident::new(
-            &format!(
-                "{}_Error_Missing_required_field_{}",
-                builder_name,
-                strip_raw_ident_prefix(field_name.to_string())
-            ),
-            proc_macro2::Span::call_site(),
+                        /// Take the value of the field out of the builder's state, leaving it unset.
+                        fn take(self) -> (Self::Value, Self::WithoutValue);
+                    }
+                }
+            });
+        // The traits are implemented outside the module, where the crate's path can be relative.
+        let field_trait_impls = self
+            .included_fields()
+            .filter(|field| field.builder_attr.flatten.is_none())
+            .map(|field| {
+                let state_trait = field.state_trait_name();
+                let can_set_trait = field.can_set_trait_name();
+                let is_unset_trait = field.is_unset_trait_name();
+                let is_set_trait = field.is_set_trait_name();
+                let index = syn::Index::from(self.included_fields().position(|f| f.ordinal == field.ordinal).unwrap());
+                let (params, any_state) = state_with(field, parse_quote!(TypedBuilderPrevious));
+                let (_, set_state) = state_with(field, parse_quote!(#crate_module_path::state::Set<TypedBuilderValue>));
+                let (_, unset_state) = state_with(field, parse_quote!(#crate_module_path::state::Unset));
+                let (destructuring, set_fields) = state_fields(field, quote!(#crate_module_path::state::Set(value)));
+                let (_, unset_fields) = state_fields(field, quote!(#crate_module_path::state::Unset));
+                // The fields of a builder that was created from an instance can be set again, and so
+                // can overridable fields.
+                let can_set_impls =
+                    (field.builder_attr.via_mutators.is_none() && field.builder_attr.setter.each.is_none()).then(|| {
+                        let overridable = field.builder_attr.setter.overridable.is_some();
+                        let states = [
+                            Some((quote!(), unset_state.clone())),
+                            Some((
+                                quote!(TypedBuilderPrevious,),
+                                state_with(
+                                    field,
+                                    parse_quote!(#crate_module_path::state::Prefilled<TypedBuilderPrevious>),
+                                )
+                                .1,
+                            )),
+                            overridable.then(|| {
+                                (
+                                    quote!(TypedBuilderPrevious,),
+                                    state_with(field, parse_quote!(#crate_module_path::state::Set<TypedBuilderPrevious>)).1,
+                                )
+                            }),
+                        ];
+                        states
+                            .into_iter()
+                            .flatten()
+                            .map(|(previous, state)| {
+                                quote! {
+                                    #[automatically_derived]
+                                    impl<#( #params, )* #previous> #builder_module_name::#can_set_trait for #state {}
+                                }
+                            })
+                            .collect::<TokenStream>()
+                    });
+                let is_set_impls = [
+                    quote!(#crate_module_path::state::Set<TypedBuilderValue>),
+                    quote!(#crate_module_path::state::Prefilled<TypedBuilderValue>),
+                ]
+                .into_iter()
+                .map(|field_state| {
+                    let (_, state) = state_with(field, parse_quote!(#field_state));
+                    quote! {
+                        #[automatically_derived]
+                        impl<#( #params, )* TypedBuilderValue> #builder_module_name::#is_set_trait for #state {
+                            type Value = TypedBuilderValue;
+
+                            fn value(&self) -> &Self::Value {
+                                &self.#index.0
+                            }
+
+                            fn take(self) -> (Self::Value, Self::WithoutValue) {
+                                let value = self.#index.0;
+                                let #destructuring = self;
+                                (value, #unset_fields)
+                            }
+                        }
+                    }
+                });
+                quote! {
+                    #[automatically_derived]
+                    impl<#( #params, )* TypedBuilderPrevious> #builder_module_name::#state_trait for #any_state {
+                        type WithValue<TypedBuilderValue> = #set_state;
+                        type WithoutValue = #unset_state;
+
+                        fn set<TypedBuilderValue>(self, value: TypedBuilderValue) -> Self::WithValue<TypedBuilderValue> {
+                            let #destructuring = self;
+                            #set_fields
+                        }
+                    }
+
+                    #can_set_impls
+
+                    #[automatically_derived]
+                    impl<#( #params, )*> #builder_module_name::#is_unset_trait for #unset_state {}
+
+                    #( #is_set_impls )*
+                }
+            });
+        let missing_fields_message = format!("missing required fields on `{builder_name}`: {{Self}}");
+        let missing_fields_label = format!(
+            "call the setters of the fields listed by name before `.{}()`",
+            self.build_method_name()
         );
-        let early_build_error_message = format!("Missing required field {}", field_name);
 
-        let build_method_name = self.build_method_name();
-        let build_method_visibility = self.build_method_visibility();
+        // The states are declared in the builder module, but they are resolved by a trait implemented
+        // next to the struct - where the types of the fields can be named, unlike in the module when
+        // they are relative paths or the struct is declared in a function body.
+        let stable_states = self.builder_attr.builder_type.stable.map(|_| {
+            // Aliases can't have bounds, and their generic parameters must all be used - which the
+            // trait does.
+            let mut alias_generics = self.generics.clone();
+            alias_generics.where_clause = None;
+            for param in &mut alias_generics.params {
+                match param {
+                    syn::GenericParam::Lifetime(param) => param.bounds.clear(),
+                    syn::GenericParam::Type(param) => {
+                        param.colon_token = None;
+                        param.bounds.clear();
+                        param.eq_token = None;
+                        param.default = None;
+                    }
+                    syn::GenericParam::Const(param) => {
+                        param.eq_token = None;
+                        param.default = None;
+                    }
+                }
+            }
+            let (_, alias_ty_generics, _) = alias_generics.split_for_impl();
+            let (_, ty_generics, _) = self.generics.split_for_impl();
+            let empty_state = self.empty_state_type();
+            let complete_state = type_tuple(self.included_fields().map(|f| {
+                if f.builder_attr.default.is_none() || f.builder_attr.via_mutators.is_some() || self.is_builder_method_arg(f) {
+                    self.set_state_type(f)
+                } else {
+                    parse_quote!(#crate_module_path::state::Unset)
+                }
+            }));
+            let built_name = self.built_name();
+            let empty_doc = format!("The state of [`{builder_name}`] as it is created, before any of the fields of `{built_name}` are set.");
+            let complete_doc = format!(
+                "The state of [`{builder_name}`] with all the required fields of `{built_name}` set, and the fields that have defaults unset."
+            );
+            let declarations = quote! {
+                #[doc(hidden)]
+                pub trait StableStates #alias_generics {
+                    type Empty;
+                    type Complete;
+                }
+
+                #[doc = #empty_doc]
+                pub type Empty #alias_generics = <() as StableStates #alias_ty_generics>::Empty;
+
+                #[doc = #complete_doc]
+                pub type Complete #alias_generics = <() as StableStates #alias_ty_generics>::Complete;
+            };
+            // Unlike the `impl` blocks of the builder, this one doesn't name the types of the fields in
+            // its header, so it doesn't get the bounds they imply.
+            let mut generics = self.generics.clone();
+            generics
+                .make_where_clause()
+                .predicates
+                .extend(self.included_fields().flat_map(|f| outlives_bounds(f.ty)));
+            let (impl_generics, _, where_clause) = generics.split_for_impl();
+            let implementation = quote! {
+                #[automatically_derived]
+                impl #impl_generics #builder_module_name::StableStates #ty_generics for () #where_clause {
+                    type Empty = #empty_state;
+                    type Complete = #complete_state;
+                }
+            };
+            (declarations, implementation)
+        });
+        let (stable_state_declarations, stable_state_impl) = stable_states.unzip();
+        let builder_module_doc = if self.builder_attr.doc {
+            let doc = format!("Traits for the states of the fields of [`{builder_name}`].");
+            quote!(#[doc = #doc])
+        } else {
+            quote!(#[doc(hidden)])
+        };
 
         quote! {
-            #[doc(hidden)]
-            #[allow(dead_code, non_camel_case_types, non_snake_case)]
-            #[allow(clippy::exhaustive_enums)]
-            pub enum #early_build_error_type_name {}
-            #[doc(hidden)]
-            #[allow(dead_code, non_camel_case_types, missing_docs, clippy::panic)]
-            #[automatically_derived]
-            impl #impl_generics #builder_name < #( #builder_generics ),* > #where_clause {
-                #[deprecated(
-                    note = #early_build_error_message
+            #builder_module_doc
+            #[allow(dead_code, non_camel_case_types, non_snake_case, unreachable_pub, missing_docs)]
+            #builder_module_visibility mod #builder_module_name {
+                #( #missing_field_markers )*
+
+                #( #field_traits )*
+
+                #[doc(hidden)]
+                #[diagnostic::on_unimplemented(
+                    message = #missing_fields_message,
+                    label = #missing_fields_label,
                 )]
-                #build_method_visibility fn #build_method_name(self, _: #early_build_error_type_name) -> ! {
-                    panic!()
-                }
+                pub trait AllRequiredFieldsSet {}
+
+                impl AllRequiredFieldsSet for () {}
+
+                #stable_state_declarations
             }
+
+            #( #field_trait_impls )*
+
+            #stable_state_impl
         }
     }
 
@@ -431,40 +2034,120 @@ impl<'a> StructInfo<'a> {
         mutator @ Mutator {
             fun: mutator_fn,
             required_fields,
+            set_fields,
+            result,
         }: &Mutator,
     ) -> syn::Result<TokenStream> {
         let StructInfo { ref builder_name, .. } = *self;
+        let crate_module_path = &self.builder_attr.crate_module_path;
+
+        if let Some(field) = set_fields.iter().find(|field| required_fields.contains(*field)) {
+            return Err(Error::new_spanned(field, "a mutator cannot both require a field and set it"));
+        }
 
         let mut required_fields = required_fields.clone();
+        let mut set_fields = set_fields.clone();
 
         let mut ty_generics = self.generic_arguments();
+        let mut output_ty_generics = self.generic_arguments();
         let mut destructuring = TokenStream::new();
+        let mut output_fields = TokenStream::new();
         let mut ty_generics_tuple = empty_type_tuple();
+        let mut output_ty_generics_tuple = empty_type_tuple();
         let mut generics = self.generics.clone();
-        let mut mutator_ty_fields = Punctuated::<_, Token![,]>::new();
-        let mut mutator_destructure_fields = Punctuated::<_, Token![,]>::new();
+        let mut mutator_ty_fields = Vec::new();
+        let mut mutator_destructure_fields = Vec::new();
+        let mut set_field_inits = Vec::new();
+        let mut fields_set = Vec::new();
         for f @ FieldInfo { name, ty, .. } in self.included_fields() {
-            if f.builder_attr.via_mutators.is_some() || required_fields.remove(f.name) {
-                ty_generics_tuple.elems.push(f.tuplized_type_ty_param());
+            if let (Some(_), Some(required)) = (f.builder_attr.flatten, required_fields.get(name)) {
+                return Err(Error::new_spanned(required, "mutators cannot require flattened fields"));
+            }
+            if let Some(set) = set_fields.take(name) {
+                if f.builder_attr.setter.skip.is_some()
+                    || f.builder_attr.via_mutators.is_some()
+                    || f.builder_attr.flatten.is_some()
+                    || self.is_receiver(f)
+                {
+                    return Err(Error::new_spanned(set, "only fields with setters can be set by mutators"));
+                }
+                // The field starts from its previous value - when the builder was prefilled, or the
+                // field is overridable and set - or from its default value, which the mutator is
+                // expected to overwrite.
+                let state_binding = f.state_binding_name();
+                set_field_inits.push(quote! {
+                    let #name: #ty = #crate_module_path::state::Optional::into_value(#state_binding, ::core::default::Default::default);
+                });
+                generics.params.push(f.generic_ty_param());
+                let generic_argument: syn::Type = f.type_ident();
+                ty_generics_tuple.elems.push(generic_argument);
+                output_ty_generics_tuple.elems.push(self.set_state_type(f));
+                mutator_ty_fields.push(quote!(#name: #ty));
+                mutator_destructure_fields.push(name);
+                quote!(#state_binding,).to_tokens(&mut destructuring);
+                quote!(#crate_module_path::state::Set(#name),).to_tokens(&mut output_fields);
+                fields_set.push((f, set.span()));
+            } else if (f.builder_attr.via_mutators.is_some() && !self.is_receiver(f)) || required_fields.remove(name) {
+                ty_generics_tuple.elems.push(self.set_state_type(f));
+                output_ty_generics_tuple.elems.push(self.set_state_type(f));
                 mutator_ty_fields.push(quote!(#name: #ty));
                 mutator_destructure_fields.push(name);
-                quote!((#name,),).to_tokens(&mut destructuring);
+                quote!(#crate_module_path::state::Set(#name),).to_tokens(&mut destructuring);
+                quote!(#crate_module_path::state::Set(#name),).to_tokens(&mut output_fields);
             } else {
                 generics.params.push(f.generic_ty_param());
                 let generic_argument: syn::Type = f.type_ident();
                 ty_generics_tuple.elems.push(generic_argument.clone());
+                output_ty_generics_tuple.elems.push(generic_argument);
                 quote!(#name,).to_tokens(&mut destructuring);
+                quote!(#name,).to_tokens(&mut output_fields);
             }
         }
-        ty_generics.push(syn::GenericArgument::Type(ty_generics_tuple.into()));
+        if let Some(unknown) = set_fields.into_iter().next() {
+            return Err(Error::new_spanned(&unknown, format!("no field named `{unknown}`")));
+        }
+        // The fields the mutator sets must be settable in the state of the builder - including with
+        // `ordered`, which the fields they are set after must be set for. Collections that are set
+        // item by item must be unset, like they must be for the mutator to start from their default.
+        let builder_module_name = self.builder_module_name();
+        let state: syn::Type = ty_generics_tuple.into();
+        let where_clause = generics.make_where_clause();
+        for (f, set_span) in &fields_set {
+            let can_set_trait = if f.builder_attr.setter.each.is_some() {
+                f.is_unset_trait_name()
+            } else {
+                f.can_set_trait_name()
+            };
+            let FieldInfo { ty, generic_ident, .. } = f;
+            where_clause.predicates.extend::<[syn::WherePredicate; 3]>([
+                parse_quote!(#state: #builder_module_name::#can_set_trait),
+                parse_quote!(#generic_ident: #crate_module_path::state::Optional<#ty>),
+                parse_quote_spanned!(*set_span=> #ty: ::core::default::Default),
+            ]);
+        }
+        let fields_set = fields_set.into_iter().map(|(f, _)| f).collect::<Vec<_>>();
+        where_clause.predicates.extend(self.ordered_set_bounds(&fields_set, &state));
+        ty_generics.push(syn::GenericArgument::Type(state));
+        output_ty_generics.push(syn::GenericArgument::Type(output_ty_generics_tuple.into()));
         let (impl_generics, _, where_clause) = generics.split_for_impl();
 
         let mutator_struct_name = format_ident!("TypedBuilderFieldMutator");
+        // The struct the mutator is a method of is declared inside the method of the builder, so
+        // it can't use the generic parameters of the builder and needs its own copy of them.
+        let mutator_generics = self.generics;
+        let mutator_where_clause = &self.generics.where_clause;
+        let (mutator_impl_generics, mutator_ty_generics, _) = self.generics.split_for_impl();
+        let mutator_phantom_generics = self.phantom_generics();
 
         let ItemFn { attrs, vis, .. } = mutator_fn;
-        let sig = mutator.outer_sig(parse_quote!(#builder_name <#ty_generics>));
+        let sig = mutator.outer_sig(parse_quote!(#builder_name <#output_ty_generics>));
         let fn_name = &sig.ident;
         let mutator_args = mutator.arguments();
+        let (question_mark, output) = if result.is_some() {
+            (quote!(?), quote!(::core::result::Result::Ok(__builder)))
+        } else {
+            (quote!(), quote!(__builder))
+        };
 
         Ok(quote! {
             #[allow(dead_code, non_camel_case_types, missing_docs)]
@@ -473,39 +2156,60 @@ impl<'a> StructInfo<'a> {
                 #(#attrs)*
                 #[allow(clippy::used_underscore_binding)]
                 #vis #sig {
-                    struct #mutator_struct_name {
-                        #mutator_ty_fields
+                    struct #mutator_struct_name #mutator_generics #mutator_where_clause {
+                        #( #mutator_ty_fields, )*
+                        __typed_builder_phantom: ::core::marker::PhantomData<(#( #mutator_phantom_generics ),*)>,
                     }
-                    impl #mutator_struct_name {
+                    impl #mutator_impl_generics #mutator_struct_name #mutator_ty_generics #mutator_where_clause {
                         #mutator_fn
                     }
 
                     let __args = (#mutator_args);
 
                     let ( #destructuring ) = self.fields;
-                    let mut __mutator = #mutator_struct_name{ #mutator_destructure_fields };
+                    #( #set_field_inits )*
+                    let mut __mutator: #mutator_struct_name #mutator_ty_generics = #mutator_struct_name {
+                        #( #mutator_destructure_fields, )*
+                        __typed_builder_phantom: ::core::marker::PhantomData,
+                    };
 
                     // This dance is required to keep mutator args and destrucutre fields from interfering.
                     {
                         let (#mutator_args) = __args;
-                        __mutator.#fn_name(#mutator_args);
+                        __mutator.#fn_name(#mutator_args) #question_mark;
                     }
 
                     let #mutator_struct_name {
-                        #mutator_destructure_fields
+                        #( #mutator_destructure_fields, )*
+                        ..
                     } = __mutator;
 
-                    #builder_name {
-                        fields: ( #destructuring ),
+                    let __builder = #builder_name {
+                        fields: ( #output_fields ),
                         phantom: self.phantom,
-                    }
+                    };
+                    #output
                 }
             }
         })
     }
 
     fn build_method_name(&self) -> TokenStream {
-        self.builder_attr.build_method.common.get_name().unwrap_or(quote!(build))
+        self.builder_attr.build_method.common.get_name().unwrap_or_else(|| {
+            let constructor = matches!(
+                self.target,
+                BuilderTarget::Function {
+                    method: Some(MethodInfo { constructor: true, .. }),
+                    ..
+                }
+            );
+            match (&self.target, self.builder_attr.build_method.error.is_some()) {
+                (BuilderTarget::Function { .. }, false) if !constructor => quote!(call),
+                (BuilderTarget::Function { .. }, true) if !constructor => quote!(try_call),
+                (_, false) => quote!(build),
+                (_, true) => quote!(try_build),
+            }
+        })
     }
 
     fn build_method_visibility(&self) -> TokenStream {
@@ -519,32 +2223,33 @@ impl<'a> StructInfo<'a> {
             ..
         } = *self;
 
+        let crate_module_path = &self.builder_attr.crate_module_path;
+        let builder_module_name = self.builder_module_name();
+        let is_required = |field: &FieldInfo<'_>| {
+            field.builder_attr.default.is_none()
+                && field.builder_attr.via_mutators.is_none()
+                && field.builder_attr.flatten.is_none()
+        };
+
         let generics = {
             let mut generics = self.generics.clone();
             for field in self.included_fields() {
-                if field.builder_attr.default.is_some() {
-                    let trait_ref = syn::TraitBound {
-                        paren_token: None,
-                        lifetimes: None,
-                        modifier: syn::TraitBoundModifier::None,
-                        path: {
-                            let mut path = self.builder_attr.crate_module_path.clone();
-                            path.segments.push(syn::PathSegment {
-                                ident: Ident::new("Optional", Span::call_site()),
-                                arguments: syn::PathArguments::AngleBracketed(syn::AngleBracketedGenericArguments {
-                                    colon2_token: None,
-                                    lt_token: Default::default(),
-                                    args: [syn::GenericArgument::Type(field.ty.clone())].into_iter().collect(),
-                                    gt_token: Default::default(),
-                                }),
-                            });
-                            path
-                        },
-                    };
-                    let mut generic_param: syn::TypeParam = field.generic_ident.clone().into();
-                    generic_param.bounds.push(trait_ref.into());
-                    generics.params.push(generic_param.into());
+                let ty = field.ty;
+                if field.builder_attr.flatten.is_some() {
+                    generics.params.push(field.generic_ty_param());
+                    continue;
                 }
+                let bound: syn::TypeParamBound = if field.builder_attr.default.is_some() {
+                    parse_quote!(#crate_module_path::state::Optional<#ty>)
+                } else if is_required(field) {
+                    let name = &field.name;
+                    parse_quote!(#crate_module_path::RequiredField<#ty, #builder_module_name::#name>)
+                } else {
+                    continue;
+                };
+                let mut generic_param: syn::TypeParam = field.generic_ident.clone().into();
+                generic_param.bounds.push(bound);
+                generics.params.push(generic_param.into());
             }
             generics
         };
@@ -555,25 +2260,24 @@ impl<'a> StructInfo<'a> {
         let modified_ty_generics = modify_types_generics_hack(&ty_generics, |args| {
             args.push(syn::GenericArgument::Type(
                 type_tuple(self.included_fields().map(|field| {
-                    if field.builder_attr.default.is_some() {
-                        field.type_ident()
+                    if field.builder_attr.via_mutators.is_some() {
+                        self.set_state_type(field)
                     } else {
-                        field.tuplized_type_ty_param()
+                        field.type_ident()
                     }
                 }))
                 .into(),
             ));
         });
 
-        let descructuring = self.included_fields().map(|f| f.name);
+        let descructuring = self.included_fields().map(FieldInfo::state_binding_name);
 
-        // The default of a field can refer to earlier-defined fields, which we handle by
-        // writing out a bunch of `let` statements first, which can each refer to earlier ones.
-        // This means that field ordering may actually be significant, which isn't ideal. We could
-        // relax that restriction by calculating a DAG of field default dependencies and
-        // reordering based on that, but for now this much simpler thing is a reasonable approach.
-        let assignments = self.fields.iter().map(|field| {
+        // The default of a field can refer to other fields, which we handle by writing out a bunch
+        // of `let` statements first, in an order where each can refer to the ones it depends on.
+        let assignments = self.resolution_order.iter().map(|&i| {
+            let field = &self.fields[i];
             let name = &field.name;
+            let state = field.state_binding_name();
 
             let maybe_mut = if let Some(span) = field.builder_attr.mutable_during_default_resolution {
                 quote_spanned!(span => mut)
@@ -585,62 +2289,526 @@ impl<'a> StructInfo<'a> {
                 if field.builder_attr.setter.skip.is_some() {
                     quote!(let #maybe_mut #name = #default;)
                 } else {
-                    let crate_module_path = &self.builder_attr.crate_module_path;
-
-                    quote!(let #maybe_mut #name = #crate_module_path::Optional::into_value(#name, || #default);)
+                    quote!(let #maybe_mut #name = #crate_module_path::state::Optional::into_value(#state, || #default);)
                 }
+            } else if field.builder_attr.flatten.is_some() {
+                quote!(let #maybe_mut #name = #crate_module_path::Build::build(#state);)
+            } else if is_required(field) {
+                quote!(let #maybe_mut #name = #crate_module_path::RequiredField::into_value(#state);)
             } else {
-                quote!(let #maybe_mut #name = #name.0;)
+                quote!(let #maybe_mut #name = #state.0;)
             }
         });
-        let field_names = self.fields.iter().map(|field| field.name);
-
         let build_method_name = self.build_method_name();
         let build_method_visibility = self.build_method_visibility();
         let build_method_doc = if self.builder_attr.doc {
-            self.builder_attr
-                .build_method
-                .common
-                .get_doc_or(|| format!("Finalise the builder and create its [`{}`] instance", name))
+            self.builder_attr.build_method.common.get_doc_or(|| {
+                if let BuilderTarget::Function { .. } = self.target {
+                    format!("Finalise the builder and call `{}` with its arguments", self.built_name())
+                } else {
+                    format!("Finalise the builder and create its [`{}`] instance", self.built_name())
+                }
+            })
         } else {
             quote!()
         };
 
-        let type_constructor = {
-            let ty_generics = ty_generics.as_turbofish();
-            quote!(#name #ty_generics)
+        let (built_value, built_type, build_method_qualifiers) = match self.target {
+            BuilderTarget::Struct | BuilderTarget::Variant(_) => {
+                (self.struct_construction(), quote!(#name #ty_generics), quote!())
+            }
+            BuilderTarget::Function {
+                item, output, method, ..
+            } => {
+                let fn_name = &item.sig.ident;
+                let fn_generics = item.sig.generics.params.iter().filter_map(|param| match param {
+                    syn::GenericParam::Lifetime(_) => None,
+                    syn::GenericParam::Type(syn::TypeParam { ident, .. })
+                    | syn::GenericParam::Const(syn::ConstParam { ident, .. }) => Some(ident),
+                });
+                let args = self.fields.iter().map(|field| &field.name);
+                let mut call = quote!(#fn_name::<#( #fn_generics ),*>(#( #args ),*));
+                if let Some(MethodInfo { self_ty, .. }) = method {
+                    call = quote!(<#self_ty>::#call);
+                }
+                if item.sig.unsafety.is_some() {
+                    call = quote!(unsafe { #call });
+                }
+                if item.sig.asyncness.is_some() {
+                    call = quote!(#call.await);
+                }
+                let syn::Signature { asyncness, unsafety, .. } = &item.sig;
+                // A method stays in its `impl` block, but a function is moved into the build method.
+                let item = method.is_none().then_some(item);
+                (
+                    quote! {
+                        {
+                            #item
+                            #call
+                        }
+                    },
+                    output.to_token_stream(),
+                    quote!(#asyncness #unsafety),
+                )
+            }
         };
 
-        let (build_method_generic, output_type, build_method_where_clause) = match &self.builder_attr.build_method.into {
-            IntoSetting::NoConversion => (None, quote!(#name #ty_generics), None),
-            IntoSetting::GenericConversion => (
-                Some(quote!(<__R>)),
-                quote!(__R),
-                Some(quote!(where #name #ty_generics: Into<__R>)),
-            ),
-            IntoSetting::TypeConversionToSpecificType(into) => (None, into.to_token_stream(), None),
+        let (build_method_generic, output_type, into_bound) = self.build_method_output(&built_type);
+        // A single bound for all the required fields, so that a single error lists all the missing
+        // ones. It is on the method rather than on the `impl`, because that's where the compiler
+        // uses the custom message of the trait.
+        let required_fields_bound = {
+            let missing_fields = self.required_fields().rev().fold(quote!(()), |rest, field| {
+                let FieldInfo {
+                    ty, name, generic_ident, ..
+                } = field;
+                quote!(<#generic_ident as #crate_module_path::RequiredField<#ty, #builder_module_name::#name>>::Report<#rest>)
+            });
+            let bound_span = self.builder_name.span();
+            let flattened_bounds = self.flattened_fields().map(|field| {
+                let FieldInfo { ty, generic_ident, .. } = field;
+                quote!(#generic_ident: #crate_module_path::Build<Output = #ty>)
+            });
+            quote_spanned!(bound_span => #missing_fields: #builder_module_name::AllRequiredFieldsSet #(, #flattened_bounds)*)
+        };
+        let build_trait_where_clause = {
+            let mut where_clause = where_clause.cloned().unwrap_or_else(|| parse_quote!(where));
+            where_clause.predicates.extend(
+                Punctuated::<syn::WherePredicate, Token![,]>::parse_terminated
+                    .parse2(required_fields_bound.clone())
+                    .unwrap(),
+            );
+            where_clause
+        };
+        let build_method_where_clause = match into_bound {
+            Some(into_bound) => quote!(where #required_fields_bound, #into_bound),
+            None => quote!(where #required_fields_bound),
+        };
+
+        // The trait can't express a generic conversion, so with `build_method(into)` it builds the
+        // type itself.
+        let (trait_output_type, build_method_turbofish) = match &self.builder_attr.build_method.into {
+            IntoSetting::GenericConversion => (built_type.clone(), Some(quote!(::<#built_type>))),
+            _ => (output_type.clone(), None),
+        };
+        let trait_output_type = match &self.builder_attr.build_method.error {
+            Some(error) => quote!(::core::result::Result<#trait_output_type, #error>),
+            None => trait_output_type,
         };
+        // `Build` has no room for `async` or `unsafe`, and implementing it would expose a build
+        // method whose visibility was restricted.
+        let build_trait_impl = (build_method_qualifiers.is_empty() && self.builder_attr.build_method.common.vis.is_none())
+            .then(|| {
+                quote! {
+                    #[allow(dead_code, non_camel_case_types, missing_docs)]
+                    #[automatically_derived]
+                    impl #impl_generics #crate_module_path::Build for #builder_name #modified_ty_generics #build_trait_where_clause {
+                        type Output = #trait_output_type;
+
+                        fn build(self) -> Self::Output {
+                            Self::#build_method_name #build_method_turbofish(self)
+                        }
+                    }
+                }
+            });
+
+        let (output_type, construction) = self.finish_build(&built_value, output_type);
 
         quote!(
             #[allow(dead_code, non_camel_case_types, missing_docs)]
             #[automatically_derived]
             impl #impl_generics #builder_name #modified_ty_generics #where_clause {
                 #build_method_doc
-                #[allow(clippy::default_trait_access, clippy::used_underscore_binding)]
-                #build_method_visibility fn #build_method_name #build_method_generic (self) -> #output_type #build_method_where_clause {
+                #[allow(clippy::default_trait_access, clippy::used_underscore_binding, clippy::redundant_closure_call)]
+                #build_method_visibility #build_method_qualifiers fn #build_method_name #build_method_generic (self) -> #output_type #build_method_where_clause {
                     let ( #(#descructuring,)* ) = self.fields;
                     #( #assignments )*
 
-                    #[allow(deprecated)]
-                    #type_constructor {
-                        #( #field_names ),*
-                    }.into()
+                    #construction
+                }
+            }
+
+            #build_trait_impl
+        )
+    }
+
+    /// The name of the runtime-checked builder, which is the name of the builder with `Runtime`
+    /// added before its `Builder` suffix.
+    fn runtime_builder_name(&self) -> Ident {
+        let builder_name = self.builder_name.to_string();
+        match builder_name.strip_suffix("Builder") {
+            Some(prefix) => format_ident!("{}RuntimeBuilder", prefix),
+            None => format_ident!("{}Runtime", builder_name),
+        }
+    }
+
+    fn runtime_builder_method_name(&self) -> Ident {
+        match self.target {
+            BuilderTarget::Variant(variant) => {
+                format_ident!(
+                    "{}_runtime_builder",
+                    to_snake_case(&strip_raw_ident_prefix(variant.to_string()))
+                )
+            }
+            _ => format_ident!("runtime_builder"),
+        }
+    }
+
+    /// A builder that holds its fields in `Option`s and checks that the required ones are set when
+    /// it is built, for when the builder is filled in by code that the types can't follow.
+    pub fn runtime_builder_impl(&self) -> syn::Result<TokenStream> {
+        if self.builder_attr.runtime.is_none() {
+            return Ok(quote!());
+        }
+        let StructInfo { vis, name, .. } = *self;
+        let crate_module_path = &self.builder_attr.crate_module_path;
+        let runtime_builder_name = self.runtime_builder_name();
+        let runtime_builder_method_name = self.runtime_builder_method_name();
+        let (impl_generics, ty_generics, where_clause) = self.generics.split_for_impl();
+        let generics = self.generics;
+
+        let required_fields = self.required_fields().collect::<Vec<_>>();
+        if required_fields.len() > 128 {
+            return Err(Error::new_spanned(
+                &required_fields[128].name,
+                "the runtime builder supports up to 128 required fields",
+            ));
+        }
+        // The position of each field in the tuple of the builder's fields.
+        let field_index = |field: &FieldInfo<'_>| {
+            let index = self.setter_fields().position(|f| f.ordinal == field.ordinal).unwrap();
+            syn::Index::from(index)
+        };
+
+        let field_types = self.setter_fields().map(|f| f.ty);
+        // The runtime builder method takes the same arguments as the builder method.
+        let (builder_method_args, arg_conversions, are_args_const) = self.builder_method_params()?;
+        let runtime_builder_method_const = are_args_const.then(|| quote!(const));
+        let init_fields_expr = self.setter_fields().map(|field| {
+            if self.is_builder_method_arg(field) {
+                let name = &field.name;
+                quote!(::core::option::Option::Some(#name))
+            } else {
+                quote!(::core::option::Option::None)
+            }
+        });
+        let phantom_generics = self.phantom_generics();
+        let default_impl = builder_method_args.is_empty().then(|| {
+            quote! {
+                #[automatically_derived]
+                impl #impl_generics ::core::default::Default for #runtime_builder_name #ty_generics #where_clause {
+                    fn default() -> Self {
+                        #name::#runtime_builder_method_name()
+                    }
+                }
+            }
+        });
+
+        let setters = self
+            .setter_fields()
+            .map(|field| -> syn::Result<TokenStream> {
+                let index = field_index(field);
+                let method_name = field.setter_method_name();
+                let doc = field.builder_attr.setter.doc.as_ref().map(|doc| quote!(#[doc = #doc]));
+                let deprecated = &field.builder_attr.deprecated;
+                if let Some(each) = &field.builder_attr.setter.each {
+                    let FieldInfo {
+                        name: field_name,
+                        ty: field_type,
+                        ..
+                    } = field;
+                    let item_type = quote!(<#field_type as ::core::iter::IntoIterator>::Item);
+                    let (item_arg_type, items_arg_type, item_expr, items_expr) = Self::each_setter_args(field, each);
+                    let collection_bounds = quote! {
+                        where
+                            #field_type: ::core::iter::Extend<#item_type> + ::core::default::Default,
+                    };
+                    let extend = |items: TokenStream| {
+                        quote! {
+                            ::core::iter::Extend::extend(
+                                self.fields.#index.get_or_insert_with(::core::default::Default::default),
+                                #items,
+                            );
+                            self
+                        }
+                    };
+                    let add_item = extend(quote!(::core::iter::once(#item_expr)));
+                    let add_items = extend(items_expr);
+                    return Ok(quote! {
+                        #deprecated
+                        #doc
+                        #[allow(clippy::used_underscore_binding)]
+                        pub fn #each (&mut self, #each: #item_arg_type) -> &mut Self #collection_bounds {
+                            #add_item
+                        }
+
+                        #deprecated
+                        #doc
+                        #[allow(clippy::used_underscore_binding)]
+                        pub fn #method_name (&mut self, #field_name: #items_arg_type) -> &mut Self #collection_bounds {
+                            #add_items
+                        }
+                    });
+                }
+                let SetterArgs {
+                    params,
+                    value,
+                    generics: method_generics,
+                    bounds,
+                    ..
+                } = self.setter_args(field)?;
+                let bounds = bounds.map(|bounds| quote!(where #bounds));
+                Ok(quote! {
+                    #deprecated
+                    #doc
+                    #[allow(clippy::used_underscore_binding)]
+                    pub fn #method_name #method_generics (&mut self, #params) -> &mut Self #bounds {
+                        self.fields.#index = ::core::option::Option::Some(#value);
+                        self
+                    }
+                })
+            })
+            .collect::<syn::Result<TokenStream>>()?;
+
+        let maybe_mut = |field: &FieldInfo<'_>| {
+            field
+                .builder_attr
+                .mutable_during_default_resolution
+                .map(|span| quote_spanned!(span => mut))
+        };
+
+        // The required fields are taken all at once, so that they can be put back if any of them
+        // is missing.
+        let take_required_fields = (!required_fields.is_empty()).then(|| {
+            let names = required_fields.iter().map(|f| f.state_binding_name()).collect::<Vec<_>>();
+            let indices = required_fields.iter().map(|f| field_index(f)).collect::<Vec<_>>();
+            let bits = 0..required_fields.len() as u32;
+            let runtime_builder_name = runtime_builder_name.to_string();
+            let required_field_names = required_fields.iter().map(|f| strip_raw_ident_prefix(f.name.to_string()));
+            quote! {
+                let ( #( #names, )* ) = match ( #( self.fields.#indices.take(), )* ) {
+                    ( #( ::core::option::Option::Some(#names), )* ) => ( #( #names, )* ),
+                    ( #( #names, )* ) => {
+                        let mut __missing = 0u128;
+                        #(
+                            if ::core::option::Option::is_none(&#names) {
+                                __missing |= 1 << #bits;
+                            }
+                        )*
+                        #( self.fields.#indices = #names; )*
+                        return ::core::result::Result::Err(::core::convert::From::from(
+                            #crate_module_path::MissingFields::new(
+                                #runtime_builder_name,
+                                &[ #( #required_field_names ),* ],
+                                __missing,
+                            ),
+                        ));
+                    }
+                };
+            }
+        });
+
+        // Like in the typestate builder, the defaults are resolved after the fields they refer to.
+        let assignments = self.resolution_order.iter().map(|&i| {
+            let field = &self.fields[i];
+            let name = &field.name;
+            let maybe_mut = maybe_mut(field);
+            if let Some(via_mutators) = &field.builder_attr.via_mutators {
+                let init = &via_mutators.init;
+                quote!(let #maybe_mut #name = #init;)
+            } else if let Some(default) = &field.builder_attr.default {
+                if field.builder_attr.setter.skip.is_some() {
+                    quote!(let #maybe_mut #name = #default;)
+                } else {
+                    let index = field_index(field);
+                    quote! {
+                        let #maybe_mut #name = match self.fields.#index.take() {
+                            ::core::option::Option::Some(#name) => #name,
+                            ::core::option::Option::None => #default,
+                        };
+                    }
+                }
+            } else {
+                let state = field.state_binding_name();
+                quote!(let #maybe_mut #name = #state;)
+            }
+        });
+
+        let built_type = quote!(#name #ty_generics);
+        let (build_method_generic, output_type, into_bound) = self.build_method_output(&built_type);
+        let (output_type, construction) = self.finish_build(&self.struct_construction(), output_type);
+        let (output_type, construction) = if self.builder_attr.build_method.error.is_some() {
+            (output_type, construction)
+        } else {
+            (
+                quote!(::core::result::Result<#output_type, #crate_module_path::MissingFields>),
+                quote!(::core::result::Result::Ok({ #construction })),
+            )
+        };
+        let build_method_where_clause = into_bound.map(|into_bound| quote!(where #into_bound));
+
+        let builder_method_visibility = first_visibility(&[
+            self.builder_attr.builder_method.common.vis.as_ref(),
+            self.builder_attr.builder_type.common.vis.as_ref(),
+            Some(vis),
+        ]);
+        let builder_type_visibility = first_visibility(&[self.builder_attr.builder_type.common.vis.as_ref(), Some(vis)]);
+        let build_method_name = self.build_method_name();
+        let build_method_visibility = self.build_method_visibility();
+        let builder_method_doc = format!(
+            "
+            Create a builder for building `{name}` that checks its required fields when it is built.
+            On the builder, call the setters of the fields in any order and as many times as needed.
+            Finally, call `.{build_method_name}()` to create the instance of `{name}`, or get the
+            names of the required fields that were not set.
+            ",
+            name = self.built_name(),
+        );
+        let (builder_type_doc, build_method_doc) = if self.builder_attr.doc {
+            (
+                {
+                    let doc = format!(
+                        "Runtime-checked builder for [`{built_name}`] instances.\n\nSee [`{name}::{runtime_builder_method_name}()`] for more info.",
+                        built_name = self.built_name(),
+                    );
+                    quote!(#[doc = #doc])
+                },
+                {
+                    let doc = format!(
+                        "Finalise the builder and create its [`{}`] instance, or report the required fields that are not set",
+                        self.built_name()
+                    );
+                    quote!(#[doc = #doc])
+                },
+            )
+        } else {
+            (quote!(#[doc(hidden)]), quote!())
+        };
+
+        Ok(quote! {
+            #[automatically_derived]
+            impl #impl_generics #name #ty_generics #where_clause {
+                #[doc = #builder_method_doc]
+                #[allow(dead_code)]
+                #builder_method_visibility #runtime_builder_method_const fn #runtime_builder_method_name(#(#builder_method_args),*) -> #runtime_builder_name #ty_generics {
+                    #arg_conversions
+                    #runtime_builder_name {
+                        fields: ( #( #init_fields_expr, )* ),
+                        phantom: ::core::marker::PhantomData,
+                    }
+                }
+            }
+
+            #builder_type_doc
+            #[allow(dead_code, non_camel_case_types, non_snake_case)]
+            #builder_type_visibility struct #runtime_builder_name #generics #where_clause {
+                fields: ( #( ::core::option::Option<#field_types>, )* ),
+                phantom: ::core::marker::PhantomData<(#( #phantom_generics ),*)>,
+            }
+
+            #default_impl
+
+            #[allow(dead_code, non_camel_case_types, missing_docs)]
+            #[automatically_derived]
+            impl #impl_generics #runtime_builder_name #ty_generics #where_clause {
+                #setters
+
+                #build_method_doc
+                #[allow(clippy::default_trait_access, clippy::used_underscore_binding, clippy::redundant_closure_call)]
+                #build_method_visibility fn #build_method_name #build_method_generic (&mut self) -> #output_type #build_method_where_clause {
+                    #take_required_fields
+                    #( #assignments )*
+
+                    #construction
                 }
             }
+        })
+    }
+
+    /// The expression that creates the struct or the enum variant, from variables named after its
+    /// fields.
+    fn struct_construction(&self) -> TokenStream {
+        let name = self.name;
+        let (_, ty_generics, _) = self.generics.split_for_impl();
+        let ty_generics = ty_generics.as_turbofish();
+        let type_constructor = if let BuilderTarget::Variant(variant) = self.target {
+            quote!(#name #ty_generics :: #variant)
+        } else {
+            quote!(#name #ty_generics)
+        };
+        let field_names = self.fields.iter().map(|field| {
+            let name = &field.name;
+            match &field.member {
+                syn::Member::Named(_) => quote!(#name),
+                member @ syn::Member::Unnamed(_) => quote!(#member: #name),
+            }
+        });
+        quote! {
+            #type_constructor {
+                #( #field_names ),*
+            }
+        }
+    }
+
+    /// The generic parameters of the build method, its output type before it is made fallible, and
+    /// the bound for converting the built type into it.
+    fn build_method_output(&self, built_type: &TokenStream) -> (Option<TokenStream>, TokenStream, Option<TokenStream>) {
+        match &self.builder_attr.build_method.into {
+            IntoSetting::NoConversion => (None, built_type.clone(), None),
+            IntoSetting::GenericConversion => (Some(quote!(<__R>)), quote!(__R), Some(quote!(#built_type: Into<__R>))),
+            IntoSetting::TypeConversionToSpecificType(into) => (None, into.to_token_stream(), None),
+        }
+    }
+
+    /// The output type of the build method, and the statements that validate the built value and
+    /// convert it into that type - once the values of all the fields are resolved.
+    fn finish_build(&self, built_value: &TokenStream, output_type: TokenStream) -> (TokenStream, TokenStream) {
+        let Some(error) = &self.builder_attr.build_method.error else {
+            return (
+                output_type,
+                quote! {
+                    #[allow(deprecated)]
+                    #built_value.into()
+                },
+            );
+        };
+        let field_validations = self.fields.iter().filter_map(|field| {
+            let name = &field.name;
+            let validate = field.builder_attr.validate.as_ref()?;
+            Some(quote!((#validate)(&#name)?;))
+        });
+        let validation = self
+            .builder_attr
+            .build_method
+            .validate
+            .as_ref()
+            .map(|validate| quote!((#validate)(&__built)?;));
+        (
+            quote!(::core::result::Result<#output_type, #error>),
+            quote! {
+                #( #field_validations )*
+                #[allow(deprecated)]
+                let __built = #built_value;
+                #validation
+                ::core::result::Result::Ok(__built.into())
+            },
         )
     }
 }
 
+/// The parameters of a field's setter, and how the value of the field is made from them.
+struct SetterArgs {
+    params: TokenStream,
+    value: TokenStream,
+    /// The generic parameters of the setter.
+    generics: Option<TokenStream>,
+    /// Bounds on the generic parameters of the setter.
+    bounds: Option<TokenStream>,
+    /// The types of the parameters for the setter's `FlattenSetter` implementation, with generic
+    /// parameters instead of `impl Trait`, and the generic parameters with their bounds.
+    flatten: Option<(Vec<TokenStream>, Vec<TokenStream>)>,
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct CommonDeclarationSettings {
     pub vis: Option<syn::Visibility>,
@@ -687,10 +2855,91 @@ impl CommonDeclarationSettings {
     }
 }
 
-/// Setting of the `into` argument.
+/// A named set of values for fields, which a builder method of its own prefills the builder with.
 #[derive(Debug, Clone)]
+pub struct Preset {
+    pub name: Ident,
+    pub fields: Vec<(Ident, syn::Expr)>,
+}
+
+impl Preset {
+    fn new(sub_attr: SubAttr) -> syn::Result<Self> {
+        let name = sub_attr.name.clone();
+        let fields = sub_attr
+            .args::<AttrArg>()?
+            .into_iter()
+            .map(|arg| {
+                let key_value = arg.key_value()?;
+                Ok((key_value.name.clone(), key_value.parse_value()?))
+            })
+            .collect::<syn::Result<_>>()?;
+        Ok(Self { name, fields })
+    }
+
+    /// The value the preset sets the field to, if it sets it.
+    fn value_of(&self, field: &FieldInfo<'_>) -> Option<&syn::Expr> {
+        self.fields
+            .iter()
+            .find(|(name, _)| *name == field.name)
+            .map(|(_, value)| value)
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct BuilderMethodSettings {
+    pub common: CommonDeclarationSettings,
+
+    /// Fields that the builder method takes as arguments, and that are set in the builder it
+    /// creates.
+    pub args: Vec<Ident>,
+}
+
+impl ApplyMeta for BuilderMethodSettings {
+    fn apply_meta(&mut self, expr: AttrArg) -> syn::Result<()> {
+        match expr.name().to_string().as_str() {
+            "args" => {
+                self.args.extend(expr.sub_attr()?.args::<Ident>()?);
+                Ok(())
+            }
+            _ => self.common.apply_meta(expr),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct BuilderTypeSettings {
+    pub common: CommonDeclarationSettings,
+
+    /// Whether to generate aliases for the builder in its states that don't depend on the number of
+    /// fields.
+    pub stable: Option<proc_macro2::Span>,
+
+    /// The name of the module with the traits for the states of the fields, instead of the name of
+    /// the builder in snake case.
+    pub module: Option<Ident>,
+}
+
+impl ApplyMeta for BuilderTypeSettings {
+    fn apply_meta(&mut self, expr: AttrArg) -> syn::Result<()> {
+        match expr.name().to_string().as_str() {
+            "stable" => {
+                self.stable = Some(expr.flag()?.span());
+                Ok(())
+            }
+            "module" => {
+                self.module = expr.key_value_or_not()?.map(|kv| kv.parse_value()).transpose()?;
+                Ok(())
+            }
+            _ => self.common.apply_meta(expr),
+        }
+    }
+}
+
+/// Setting of the `into` argument.
+#[derive(Debug, Default, Clone)]
 pub enum IntoSetting {
     /// Do not run any conversion on the built value.
+    #[default]
     NoConversion,
     /// Convert the build value into the generic parameter passed to the `build` method.
     GenericConversion,
@@ -698,18 +2947,39 @@ pub enum IntoSetting {
     TypeConversionToSpecificType(syn::TypePath),
 }
 
-impl Default for IntoSetting {
-    fn default() -> Self {
-        Self::NoConversion
-    }
-}
-
 #[derive(Debug, Default, Clone)]
 pub struct BuildMethodSettings {
     pub common: CommonDeclarationSettings,
 
     /// Whether to convert the built type into another while finishing the build.
     pub into: IntoSetting,
+
+    /// Function that checks the built instance before it is returned.
+    pub validate: Option<syn::Expr>,
+
+    /// Error type of a fallible build method.
+    pub error: Option<syn::Type>,
+}
+
+impl BuildMethodSettings {
+    /// Make sure the validation settings of the build method and of the fields can be expressed
+    /// together.
+    fn check_validation<'b>(&self, mut field_validators: impl Iterator<Item = &'b syn::Expr>) -> syn::Result<()> {
+        if self.error.is_none() {
+            if let Some(validate) = self.validate.as_ref().or_else(|| field_validators.next()) {
+                return Err(Error::new_spanned(
+                    validate,
+                    "`validate` requires setting the error type with `build_method(error = ...)`",
+                ));
+            }
+        } else if let IntoSetting::GenericConversion = self.into {
+            return Err(Error::new_spanned(
+                self.error.as_ref(),
+                "a fallible build method needs a concrete output type - use `build_method(into = ...)` instead of `build_method(into)`",
+            ));
+        }
+        Ok(())
+    }
 }
 
 impl ApplyMeta for BuildMethodSettings {
@@ -727,6 +2997,26 @@ impl ApplyMeta for BuildMethodSettings {
                 }
                 _ => Err(expr.incorrect_type()),
             },
+            "validate" => {
+                if let AttrArg::Flag(name) = expr {
+                    return Err(Error::new_spanned(
+                        name,
+                        "`validate` needs the function that checks the built value: `validate = ...`",
+                    ));
+                }
+                self.validate = expr.key_value_or_not()?.map(|kv| kv.parse_value()).transpose()?;
+                Ok(())
+            }
+            "error" => {
+                if let AttrArg::Flag(name) = expr {
+                    return Err(Error::new_spanned(
+                        name,
+                        "`error` needs the error type of the build method: `error = ...`",
+                    ));
+                }
+                self.error = expr.key_value_or_not()?.map(|kv| kv.parse_value()).transpose()?;
+                Ok(())
+            }
             _ => self.common.apply_meta(expr),
         }
     }
@@ -738,10 +3028,10 @@ pub struct TypeBuilderAttr<'a> {
     pub doc: bool,
 
     /// Customize builder method, ex. visibility, name
-    pub builder_method: CommonDeclarationSettings,
+    pub builder_method: BuilderMethodSettings,
 
     /// Customize builder type, ex. visibility, name
-    pub builder_type: CommonDeclarationSettings,
+    pub builder_type: BuilderTypeSettings,
 
     /// Customize build method, ex. visibility, name
     pub build_method: BuildMethodSettings,
@@ -752,6 +3042,22 @@ pub struct TypeBuilderAttr<'a> {
 
     /// Functions that are able to mutate fields in the builder that are already set
     pub mutators: Vec<Mutator>,
+
+    /// Whether to also generate a builder that checks the required fields at runtime.
+    pub runtime: Option<proc_macro2::Span>,
+
+    /// Whether the required fields must be set in the order they are declared in, before the
+    /// fields that have defaults.
+    pub ordered: Option<proc_macro2::Span>,
+
+    /// Alternative builder methods, which create the builder with some of the fields prefilled.
+    pub presets: Vec<Preset>,
+
+    /// Whether other structs can flatten this one.
+    pub flattenable: Option<proc_macro2::Span>,
+
+    /// Whether to generate `into_builder()` and `to_builder()` methods on the struct.
+    pub into_builder: Option<proc_macro2::Span>,
 }
 
 impl Default for TypeBuilderAttr<'_> {
@@ -764,12 +3070,17 @@ impl Default for TypeBuilderAttr<'_> {
             field_defaults: Default::default(),
             crate_module_path: syn::parse_quote!(::typed_builder),
             mutators: Default::default(),
+            runtime: None,
+            ordered: None,
+            presets: Vec::new(),
+            flattenable: None,
+            into_builder: None,
         }
     }
 }
 
-impl TypeBuilderAttr<'_> {
-    pub fn new(attrs: &[syn::Attribute]) -> syn::Result<Self> {
+impl<'a> TypeBuilderAttr<'a> {
+    pub fn new(attrs: impl IntoIterator<Item = &'a syn::Attribute>) -> syn::Result<Self> {
         let mut result = Self::default();
 
         for attr in attrs {
@@ -787,11 +3098,25 @@ impl TypeBuilderAttr<'_> {
             result.apply_subsections(list)?;
         }
 
-        if result.builder_type.doc.is_some() || result.build_method.common.doc.is_some() {
-            result.doc = true;
+        Ok(result.implied_settings())
+    }
+
+    /// Parse the arguments of an attribute macro, e.g. `#[builder_fn(...)]`.
+    pub fn from_args(args: TokenStream) -> syn::Result<Self> {
+        let mut result = Self::default();
+
+        for arg in Punctuated::<AttrArg, Token![,]>::parse_terminated.parse2(args)? {
+            result.apply_meta(arg)?;
         }
 
-        Ok(result)
+        Ok(result.implied_settings())
+    }
+
+    fn implied_settings(mut self) -> Self {
+        if self.builder_type.common.doc.is_some() || self.build_method.common.doc.is_some() {
+            self.doc = true;
+        }
+        self
     }
 }
 
@@ -824,7 +3149,38 @@ impl ApplyMeta for TypeBuilderAttr<'_> {
                 self.mutators.extend(expr.sub_attr()?.undelimited()?);
                 Ok(())
             }
-            "field_defaults" => self.field_defaults.apply_sub_attr(expr.sub_attr()?),
+            "runtime" => {
+                self.runtime = Some(expr.flag()?.span());
+                Ok(())
+            }
+            "ordered" => {
+                self.ordered = Some(expr.flag()?.span());
+                Ok(())
+            }
+            "flattenable" => {
+                self.flattenable = Some(expr.flag()?.span());
+                Ok(())
+            }
+            "into_builder" => {
+                self.into_builder = Some(expr.flag()?.span());
+                Ok(())
+            }
+            "presets" => {
+                for preset in expr.sub_attr()?.args::<AttrArg>()? {
+                    self.presets.push(Preset::new(preset.sub_attr()?)?);
+                }
+                Ok(())
+            }
+            "field_defaults" => {
+                self.field_defaults.apply_sub_attr(expr.sub_attr()?)?;
+                if let Some(name) = &self.field_defaults.setter.name {
+                    return Err(Error::new_spanned(
+                        name,
+                        "setter(name = ...) renames a single setter and cannot be set in field_defaults",
+                    ));
+                }
+                Ok(())
+            }
             "builder_method" => self.builder_method.apply_sub_attr(expr.sub_attr()?),
             "builder_type" => self.builder_type.apply_sub_attr(expr.sub_attr()?),
             "build_method" => self.build_method.apply_sub_attr(expr.sub_attr()?),
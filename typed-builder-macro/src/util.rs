@@ -1,13 +1,15 @@
 use std::iter;
 
-use proc_macro2::{Ident, Span, TokenStream, TokenTree};
+use proc_macro2::{Delimiter, Ident, Span, TokenStream, TokenTree};
 use quote::{format_ident, ToTokens};
 use syn::{
     parenthesized,
     parse::{Parse, ParseStream, Parser},
     punctuated::Punctuated,
     spanned::Spanned,
-    token, Attribute, Error, Pat, PatIdent, Token,
+    token,
+    visit::Visit,
+    Attribute, Error, Pat, PatIdent, Token,
 };
 
 pub fn path_to_single_string(path: &syn::Path) -> Option<String> {
@@ -38,14 +40,6 @@ pub fn ident_to_type(ident: syn::Ident) -> syn::Type {
     syn::Type::Path(syn::TypePath { qself: None, path })
 }
 
-pub fn empty_type() -> syn::Type {
-    syn::TypeTuple {
-        paren_token: Default::default(),
-        elems: Default::default(),
-    }
-    .into()
-}
-
 pub fn type_tuple(elems: impl Iterator<Item = syn::Type>) -> syn::TypeTuple {
     let mut result = syn::TypeTuple {
         paren_token: Default::default(),
@@ -86,6 +80,34 @@ pub fn strip_raw_ident_prefix(mut name: String) -> String {
     name
 }
 
+pub fn to_snake_case(name: &str) -> String {
+    let chars = name.chars().collect::<Vec<_>>();
+    let mut result = String::with_capacity(name.len());
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_uppercase() {
+            let after_lowercase = i > 0 && (chars[i - 1].is_lowercase() || chars[i - 1].is_ascii_digit());
+            let ends_acronym = i > 0 && chars[i - 1].is_uppercase() && chars.get(i + 1).is_some_and(|next| next.is_lowercase());
+            if after_lowercase || ends_acronym {
+                result.push('_');
+            }
+            result.extend(c.to_lowercase());
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+pub fn to_pascal_case(name: &str) -> String {
+    name.split('_')
+        .flat_map(|word| {
+            let mut chars = word.chars();
+            chars.next().map(|first| first.to_uppercase().chain(chars))
+        })
+        .flatten()
+        .collect()
+}
+
 pub fn first_visibility(visibilities: &[Option<&syn::Visibility>]) -> proc_macro2::TokenStream {
     let vis = visibilities
         .iter()
@@ -354,6 +376,247 @@ pub trait ApplyMeta {
     }
 }
 
+/// The identifiers in the expression that may refer to local variables from outside of it - that
+/// is, the paths of a single identifier, except for functions that are called by name and for
+/// variables that are bound inside the expression where they are in scope. The tokens of macros are not parsed, so in them
+/// all the identifiers count except for those that are field or method names, path segments, macro
+/// names, field names in struct expressions, or followed by arguments.
+pub fn variable_idents(expr: &syn::Expr) -> Vec<Ident> {
+    fn collect_from_tokens(tokens: TokenStream, idents: &mut Vec<Ident>) {
+        let tokens = tokens.into_iter().collect::<Vec<_>>();
+        let is_punct = |token: Option<&TokenTree>, chars: &[char]| matches!(token, Some(TokenTree::Punct(punct)) if chars.contains(&punct.as_char()));
+        for (i, token) in tokens.iter().enumerate() {
+            match token {
+                TokenTree::Group(group) => collect_from_tokens(group.stream(), idents),
+                TokenTree::Ident(ident) => {
+                    let previous = i.checked_sub(1).and_then(|i| tokens.get(i));
+                    let is_called =
+                        matches!(tokens.get(i + 1), Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Parenthesis);
+                    if !is_punct(previous, &['.', ':']) && !is_punct(tokens.get(i + 1), &[':', '!']) && !is_called {
+                        idents.push(ident.clone());
+                    }
+                }
+                TokenTree::Punct(_) | TokenTree::Literal(_) => {}
+            }
+        }
+    }
+
+    /// The identifiers that the pattern binds.
+    fn bound_idents(pat: &Pat) -> Vec<Ident> {
+        #[derive(Default)]
+        struct BoundIdents(Vec<Ident>);
+
+        impl<'ast> Visit<'ast> for BoundIdents {
+            fn visit_pat_ident(&mut self, pat: &'ast PatIdent) {
+                self.0.push(pat.ident.clone());
+                syn::visit::visit_pat_ident(self, pat);
+            }
+        }
+
+        let mut visitor = BoundIdents::default();
+        visitor.visit_pat(pat);
+        visitor.0
+    }
+
+    /// The variables bound inside the expression are tracked per scope, so that they only hide the
+    /// variables from outside of it where they are in scope.
+    #[derive(Default)]
+    struct VariableIdents {
+        used: Vec<Ident>,
+        scopes: Vec<Vec<Ident>>,
+    }
+
+    impl VariableIdents {
+        fn use_ident(&mut self, ident: &Ident) {
+            if !self.scopes.iter().flatten().any(|bound| bound == ident) {
+                self.used.push(ident.clone());
+            }
+        }
+
+        fn bind(&mut self, pat: &Pat) {
+            let bound = bound_idents(pat);
+            match self.scopes.last_mut() {
+                Some(scope) => scope.extend(bound),
+                None => self.scopes.push(bound),
+            }
+        }
+
+        fn in_scope(&mut self, f: impl FnOnce(&mut Self)) {
+            self.scopes.push(Vec::new());
+            f(self);
+            self.scopes.pop();
+        }
+    }
+
+    impl<'ast> Visit<'ast> for VariableIdents {
+        fn visit_expr_path(&mut self, expr: &'ast syn::ExprPath) {
+            if let (None, Some(ident)) = (&expr.qself, expr.path.get_ident()) {
+                self.use_ident(ident);
+            }
+            syn::visit::visit_expr_path(self, expr);
+        }
+
+        fn visit_expr_call(&mut self, call: &'ast syn::ExprCall) {
+            match &*call.func {
+                syn::Expr::Path(func) if func.qself.is_none() && func.path.get_ident().is_some() => {}
+                func => self.visit_expr(func),
+            }
+            for arg in &call.args {
+                self.visit_expr(arg);
+            }
+        }
+
+        // Patterns don't use variables, they bind them - which the expressions that introduce them
+        // take care of.
+        fn visit_pat(&mut self, _: &'ast Pat) {}
+
+        // Items can't use local variables.
+        fn visit_item(&mut self, _: &'ast syn::Item) {}
+
+        fn visit_block(&mut self, block: &'ast syn::Block) {
+            self.in_scope(|this| syn::visit::visit_block(this, block));
+        }
+
+        fn visit_local(&mut self, local: &'ast syn::Local) {
+            // The variables are only in scope after the statement.
+            if let Some(init) = &local.init {
+                self.visit_expr(&init.expr);
+                if let Some((_, diverge)) = &init.diverge {
+                    self.visit_expr(diverge);
+                }
+            }
+            self.bind(&local.pat);
+        }
+
+        fn visit_expr_let(&mut self, expr: &'ast syn::ExprLet) {
+            // Binds in the scope of the `if` or `while` it's the condition of.
+            self.visit_expr(&expr.expr);
+            self.bind(&expr.pat);
+        }
+
+        fn visit_expr_if(&mut self, expr: &'ast syn::ExprIf) {
+            self.in_scope(|this| {
+                this.visit_expr(&expr.cond);
+                this.visit_block(&expr.then_branch);
+            });
+            if let Some((_, else_branch)) = &expr.else_branch {
+                self.visit_expr(else_branch);
+            }
+        }
+
+        fn visit_expr_while(&mut self, expr: &'ast syn::ExprWhile) {
+            self.in_scope(|this| {
+                this.visit_expr(&expr.cond);
+                this.visit_block(&expr.body);
+            });
+        }
+
+        fn visit_expr_for_loop(&mut self, expr: &'ast syn::ExprForLoop) {
+            self.visit_expr(&expr.expr);
+            self.in_scope(|this| {
+                this.bind(&expr.pat);
+                this.visit_block(&expr.body);
+            });
+        }
+
+        fn visit_arm(&mut self, arm: &'ast syn::Arm) {
+            self.in_scope(|this| {
+                this.bind(&arm.pat);
+                if let Some((_, guard)) = &arm.guard {
+                    this.visit_expr(guard);
+                }
+                this.visit_expr(&arm.body);
+            });
+        }
+
+        fn visit_expr_closure(&mut self, closure: &'ast syn::ExprClosure) {
+            self.in_scope(|this| {
+                for input in &closure.inputs {
+                    this.bind(input);
+                }
+                this.visit_expr(&closure.body);
+            });
+        }
+
+        fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+            let mut idents = Vec::new();
+            collect_from_tokens(mac.tokens.clone(), &mut idents);
+            for ident in &idents {
+                self.use_ident(ident);
+            }
+        }
+    }
+
+    let mut visitor = VariableIdents::default();
+    visitor.visit_expr(expr);
+    visitor.used
+}
+
+/// Whether the type refers to any of the lifetime or type parameters of the generics.
+pub fn type_uses_generics(ty: &syn::Type, generics: &syn::Generics) -> bool {
+    struct UsesGenerics<'a> {
+        generics: &'a syn::Generics,
+        found: bool,
+    }
+
+    impl<'ast> Visit<'ast> for UsesGenerics<'_> {
+        fn visit_lifetime(&mut self, lifetime: &'ast syn::Lifetime) {
+            self.found |= self.generics.lifetimes().any(|param| param.lifetime == *lifetime);
+        }
+
+        fn visit_type_path(&mut self, type_path: &'ast syn::TypePath) {
+            if type_path.qself.is_none() && type_path.path.leading_colon.is_none() {
+                if let Some(first) = type_path.path.segments.first() {
+                    self.found |= self.generics.type_params().any(|param| param.ident == first.ident);
+                }
+            }
+            syn::visit::visit_type_path(self, type_path);
+        }
+    }
+
+    let mut visitor = UsesGenerics { generics, found: false };
+    visitor.visit_type(ty);
+    visitor.found
+}
+
+/// The outlives bounds that the type needs to be well-formed, and that an `impl` which only names it
+/// in its body doesn't imply: the types behind references must outlive their lifetimes, and so -
+/// conservatively - must the type arguments of paths that also take lifetimes, such as `Cow<'a, T>`.
+pub fn outlives_bounds(ty: &syn::Type) -> Vec<syn::WherePredicate> {
+    #[derive(Default)]
+    struct OutlivesBounds {
+        bounds: Vec<syn::WherePredicate>,
+    }
+
+    impl<'ast> Visit<'ast> for OutlivesBounds {
+        fn visit_type_reference(&mut self, reference: &'ast syn::TypeReference) {
+            if let Some(lifetime) = &reference.lifetime {
+                let elem = &reference.elem;
+                self.bounds.push(syn::parse_quote!(#elem: #lifetime));
+            }
+            syn::visit::visit_type_reference(self, reference);
+        }
+
+        fn visit_angle_bracketed_generic_arguments(&mut self, arguments: &'ast syn::AngleBracketedGenericArguments) {
+            for lifetime in arguments.args.iter().filter_map(|arg| match arg {
+                syn::GenericArgument::Lifetime(lifetime) if lifetime.ident != "_" => Some(lifetime),
+                _ => None,
+            }) {
+                for arg in &arguments.args {
+                    if let syn::GenericArgument::Type(ty) = arg {
+                        self.bounds.push(syn::parse_quote!(#ty: #lifetime));
+                    }
+                }
+            }
+            syn::visit::visit_angle_bracketed_generic_arguments(self, arguments);
+        }
+    }
+
+    let mut visitor = OutlivesBounds::default();
+    visitor.visit_type(ty);
+    visitor.bounds
+}
+
 pub fn pat_to_ident(i: usize, pat: &Pat) -> Ident {
     if let Pat::Ident(PatIdent { ident, .. }) = pat {
         ident.clone()